@@ -0,0 +1,312 @@
+use std::f32::consts::TAU;
+
+use crate::*;
+
+const OVERLAY_SEGMENTS: usize = 12;
+const OVERLAY_SIZE: f32 = 40.0;
+
+#[derive(Reflect, FromReflect, Clone, Copy, Debug)]
+pub enum AbilityKind {
+    /// Fires a volley of bullets spread over the targets in range
+    Barrage { shots: u32 },
+    /// Slows every target near the tower's current target
+    Net {
+        radius: f32,
+        multiplier: f32,
+        seconds: f32,
+    },
+}
+
+impl Default for AbilityKind {
+    fn default() -> Self {
+        AbilityKind::Barrage { shots: 1 }
+    }
+}
+
+impl AbilityKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AbilityKind::Barrage { .. } => "Barrage",
+            AbilityKind::Net { .. } => "Net",
+        }
+    }
+}
+
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct TowerAbility {
+    pub kind: AbilityKind,
+    pub cooldown: Timer,
+}
+
+impl TowerAbility {
+    /// Abilities are ready as soon as the tower is built
+    pub fn new(kind: AbilityKind, cooldown_seconds: f32) -> Self {
+        let mut cooldown = Timer::from_seconds(cooldown_seconds, TimerMode::Once);
+        cooldown.tick(cooldown.duration());
+        TowerAbility { kind, cooldown }
+    }
+
+    pub fn ready(&self) -> bool {
+        self.cooldown.finished()
+    }
+}
+
+/// Movement multiplier on a target, the strongest slow replaces a weaker one
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Slowed {
+    pub multiplier: f32,
+    pub timer: Timer,
+}
+
+/// Cooldown ring drawn over a tower while its ability recharges
+#[derive(Component)]
+pub struct AbilityOverlay {
+    pub tower: Entity,
+}
+
+#[derive(Component)]
+pub struct AbilityOverlaySegment(pub usize);
+
+pub struct AbilityPlugin;
+
+impl Plugin for AbilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TowerAbility>()
+            .register_type::<AbilityKind>()
+            .register_type::<Slowed>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Gameplay)
+                    .with_system(ability_hotkey)
+                    .with_system(spawn_ability_overlays)
+                    .with_system(update_ability_overlays.after(spawn_ability_overlays)),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Actions.set().with_system(use_abilities),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Status
+                    .set()
+                    .with_system(tick_ability_cooldowns)
+                    .with_system(tick_slowed),
+            );
+    }
+}
+
+fn ability_hotkey(
+    input: ActionInput,
+    game_time: Res<GameTime>,
+    towers: Query<(&Selection, &Transform), With<TowerAbility>>,
+    mut actions: EventWriter<PlayerActionRequest>,
+) {
+    if game_time.paused || !input.just_pressed(InputAction::UseAbility) {
+        return;
+    }
+    for (selection, transform) in &towers {
+        if selection.selected() {
+            actions.send(PlayerActionRequest(PlayerAction::UseAbility {
+                tower: transform.translation,
+            }));
+        }
+    }
+}
+
+fn tick_ability_cooldowns(mut abilities: Query<&mut TowerAbility>, time: Res<GameTime>) {
+    for mut ability in &mut abilities {
+        ability.cooldown.tick(time.delta());
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn use_abilities(
+    mut commands: Commands,
+    mut actions: EventReader<PlayerActionEvent>,
+    mut towers: Query<(Entity, &mut TowerAbility, &Tower, &TowerType, &Transform)>,
+    targets: TargetCandidates,
+    slowed: Query<&Slowed>,
+    assets: Res<GameAssets>,
+    asset_server: Res<AssetServer>,
+) {
+    for PlayerActionEvent(action) in actions.iter() {
+        let PlayerAction::UseAbility { tower: position } = action else {
+            continue;
+        };
+        let found = entity_at(
+            *position,
+            towers
+                .iter()
+                .map(|(entity, _, _, _, transform)| (entity, transform)),
+        );
+        let Some(Ok((tower_entity, mut ability, tower, tower_type, transform))) =
+            found.map(|entity| towers.get_mut(entity))
+        else {
+            continue;
+        };
+        if !ability.ready() {
+            continue;
+        }
+        let from = transform.translation + tower.bullet_offset;
+
+        let used = match ability.kind {
+            AbilityKind::Barrage { shots } => {
+                let mut in_range: Vec<_> = targets
+                    .iter()
+                    .filter(|(entity, ..)| tower.can_target(from, *entity, &targets))
+                    .map(|(_, target_transform, ..)| target_transform.translation)
+                    .collect();
+                in_range.sort_by_key(|position| FloatOrd(Vec3::distance(*position, from)));
+                for position in in_range.iter().cycle().take(shots as usize) {
+                    let Some((model, mut bullet)) =
+                        tower_type.get_bullet(*position - from, &assets)
+                    else {
+                        break;
+                    };
+                    bullet.damage = tower.buffed_damage(bullet.damage);
+                    spawn_bullet(
+                        &mut commands,
+                        tower_entity,
+                        tower.bullet_offset,
+                        model,
+                        bullet,
+                        None,
+                    );
+                }
+                !in_range.is_empty()
+            }
+            AbilityKind::Net {
+                radius,
+                multiplier,
+                seconds,
+            } => {
+                let center = tower
+                    .choose_target(from, &targets)
+                    .and_then(|target| targets.get(target).ok())
+                    .map(|(_, target_transform, ..)| target_transform.translation);
+                if let Some(center) = center {
+                    for (entity, target_transform, ..) in &targets {
+                        let stronger = slowed
+                            .get(entity)
+                            .map_or(true, |slowed| slowed.multiplier >= multiplier);
+                        if stronger && Vec3::distance(target_transform.translation, center) < radius
+                        {
+                            commands.entity(entity).insert(Slowed {
+                                multiplier,
+                                timer: Timer::from_seconds(seconds, TimerMode::Once),
+                            });
+                        }
+                    }
+                    spawn_popup(&mut commands, &asset_server, "NET", Color::GREEN, center);
+                }
+                center.is_some()
+            }
+        };
+
+        //Nothing in range, keep the charge
+        if used {
+            ability.cooldown.reset();
+        }
+    }
+}
+
+fn tick_slowed(
+    mut commands: Commands,
+    mut targets: Query<(Entity, &mut Slowed)>,
+    time: Res<GameTime>,
+) {
+    for (entity, mut slowed) in &mut targets {
+        slowed.timer.tick(time.delta());
+        if slowed.timer.just_finished() {
+            commands.entity(entity).remove::<Slowed>();
+        }
+    }
+}
+
+fn spawn_ability_overlays(mut commands: Commands, towers: Query<Entity, Added<TowerAbility>>) {
+    for tower in &towers {
+        commands
+            .spawn(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(OVERLAY_SIZE), Val::Px(OVERLAY_SIZE)),
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                // Hidden until it has been placed over the tower
+                visibility: Visibility::INVISIBLE,
+                ..default()
+            })
+            .insert(AbilityOverlay { tower })
+            .insert(Name::new("AbilityOverlay"))
+            .with_children(|commands| {
+                let center = OVERLAY_SIZE / 2.0;
+                let dot = 6.0;
+                for i in 0..OVERLAY_SEGMENTS {
+                    //Clockwise from the top like a clock face
+                    let angle = TAU * i as f32 / OVERLAY_SEGMENTS as f32;
+                    commands
+                        .spawn(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(dot), Val::Px(dot)),
+                                position_type: PositionType::Absolute,
+                                position: UiRect {
+                                    left: Val::Px(center + 15.0 * angle.sin() - dot / 2.0),
+                                    bottom: Val::Px(center + 15.0 * angle.cos() - dot / 2.0),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            background_color: Color::rgba(0.1, 0.1, 0.1, 0.8).into(),
+                            ..default()
+                        })
+                        .insert(AbilityOverlaySegment(i));
+                }
+            });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_ability_overlays(
+    mut commands: Commands,
+    mut overlays: Query<(Entity, &AbilityOverlay, &mut Style, &mut Visibility)>,
+    mut segments: Query<
+        (&AbilityOverlaySegment, &Parent, &mut Visibility),
+        Without<AbilityOverlay>,
+    >,
+    towers: Query<(&TowerAbility, &GlobalTransform)>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    let (camera, camera_transform) = camera.single();
+    for (entity, overlay, mut style, mut visibility) in &mut overlays {
+        //Tower was sold
+        let Ok((ability, transform)) = towers.get(overlay.tower) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+
+        let screen_position = camera.world_to_viewport(
+            camera_transform,
+            transform.translation() + Vec3::new(0.0, 1.2, 0.0),
+        );
+        match screen_position {
+            Some(screen_position) if !ability.ready() => {
+                visibility.is_visible = true;
+                style.position = UiRect {
+                    left: Val::Px(screen_position.x - OVERLAY_SIZE / 2.0),
+                    bottom: Val::Px(screen_position.y - OVERLAY_SIZE / 2.0),
+                    ..default()
+                };
+            }
+            _ => visibility.is_visible = false,
+        }
+
+        //The ring empties as the ability recharges
+        let lit = (ability.cooldown.percent_left() * OVERLAY_SEGMENTS as f32).ceil() as usize;
+        for (segment, parent, mut segment_visibility) in &mut segments {
+            if parent.get() == entity {
+                segment_visibility.is_visible = segment.0 < lit;
+            }
+        }
+    }
+}
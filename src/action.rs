@@ -0,0 +1,56 @@
+use crate::*;
+
+/// Everything the player can do that changes the game.
+/// Towers are found by where they stand since entities are different every run.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum PlayerAction {
+    Build {
+        site: Vec3,
+        tower_type: TowerType,
+    },
+    Sell {
+        tower: Vec3,
+    },
+    Upgrade {
+        tower: Vec3,
+    },
+    CyclePriority {
+        tower: Vec3,
+    },
+    UseAbility {
+        tower: Vec3,
+    },
+    /// Targeted spells carry the point on the ground they were aimed at
+    CastSpell {
+        spell: SpellType,
+        position: Option<Vec3>,
+    },
+    /// Starts the next wave without waiting for the break to end
+    CallWave,
+}
+
+/// Sent by the UI, only turns into a `PlayerActionEvent` when the simulation picks it up
+pub struct PlayerActionRequest(pub PlayerAction);
+
+/// An action carried out this tick, either from the player or from a replay
+pub struct PlayerActionEvent(pub PlayerAction);
+
+/// The entity standing at a tower site, if any
+pub fn entity_at<'a>(
+    position: Vec3,
+    entities: impl IntoIterator<Item = (Entity, &'a Transform)>,
+) -> Option<Entity> {
+    entities
+        .into_iter()
+        .find(|(_, transform)| transform.translation.distance(position) < 0.01)
+        .map(|(entity, _)| entity)
+}
+
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_simulation_event::<PlayerActionRequest>()
+            .add_simulation_event::<PlayerActionEvent>();
+    }
+}
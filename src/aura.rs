@@ -0,0 +1,46 @@
+use crate::*;
+
+/// Boosts the stats of other towers in range.
+/// Auras of the same kind don't stack, each stat takes the biggest bonus from any aura reaching the tower.
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct TowerAura {
+    pub radius: f32,
+    pub fire_rate_bonus: f32,
+    pub range_bonus: f32,
+    pub damage_bonus: f32,
+}
+
+pub struct TowerAuraPlugin;
+
+impl Plugin for TowerAuraPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TowerAura>().add_system_set_to_stage(
+            SimulationStage,
+            SimulationStep::Status.set().with_system(apply_tower_auras),
+        );
+    }
+}
+
+// Cheap enough to redo every tick, towers times auras. Runs after actions and before
+// towers use the buffs, but auras built or sold this tick are commands that only land at
+// the end of it, so they count from the next tick on.
+fn apply_tower_auras(
+    mut towers: Query<(Entity, &mut Tower, &Transform)>,
+    auras: Query<(Entity, &TowerAura, &Transform)>,
+) {
+    for (entity, mut tower, transform) in &mut towers {
+        let mut buffs = TowerBuffs::default();
+        for (aura_entity, aura, aura_transform) in &auras {
+            if aura_entity == entity
+                || Vec3::distance(transform.translation, aura_transform.translation) > aura.radius
+            {
+                continue;
+            }
+            buffs.fire_rate = buffs.fire_rate.max(1.0 + aura.fire_rate_bonus);
+            buffs.range = buffs.range.max(1.0 + aura.range_bonus);
+            buffs.damage = buffs.damage.max(1.0 + aura.damage_bonus);
+        }
+        tower.buffs = buffs;
+    }
+}
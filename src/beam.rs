@@ -0,0 +1,147 @@
+use crate::*;
+
+/// Towers with a beam lock onto one target and damage it continuously, ramping up the longer they hold it
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Beam {
+    pub damage_per_second: f32,
+    /// Added to the damage multiplier for every second on the same target
+    pub ramp_per_second: f32,
+    pub max_multiplier: f32,
+    pub damage_type: DamageType,
+    #[reflect(ignore)]
+    pub locked: Option<Entity>,
+    pub hold_seconds: f32,
+    /// Health is whole numbers so fractions of damage wait here
+    pub pending_damage: f32,
+}
+
+impl Beam {
+    pub fn multiplier(&self) -> f32 {
+        (1.0 + self.ramp_per_second * self.hold_seconds).min(self.max_multiplier)
+    }
+}
+
+#[derive(Component)]
+pub struct BeamVisual;
+
+pub struct BeamPlugin;
+
+impl Plugin for BeamPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Beam>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Gameplay)
+                    .with_system(spawn_beam_visuals)
+                    .with_system(update_beam_visuals),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Attack.set().with_system(beam_damage),
+            );
+    }
+}
+
+fn spawn_beam_visuals(
+    mut commands: Commands,
+    beams: Query<Entity, Added<Beam>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for entity in &beams {
+        // Each beam gets its own material so it can glow brighter as it ramps up
+        let material = materials.add(StandardMaterial {
+            base_color: Color::ORANGE_RED,
+            unlit: true,
+            ..default()
+        });
+        commands.entity(entity).with_children(|commands| {
+            commands
+                .spawn(PbrBundle {
+                    // Unit length along z so it can be stretched to the target
+                    mesh: meshes.add(Mesh::from(shape::Box::new(0.06, 0.06, 1.0))),
+                    material,
+                    visibility: Visibility::INVISIBLE,
+                    ..default()
+                })
+                .insert(BeamVisual)
+                .insert(Name::new("Beam"));
+        });
+    }
+}
+
+fn beam_damage(
+    mut towers: Query<(&Tower, &mut Beam, &TowerType, &Transform)>,
+    targets: TargetCandidates,
+    mut damage_events: EventWriter<DamageEvent>,
+    time: Res<GameTime>,
+) {
+    for (tower, mut beam, tower_type, transform) in &mut towers {
+        let beam_start = transform.translation + tower.bullet_offset;
+
+        let still_locked = beam
+            .locked
+            .is_some_and(|target| tower.can_target(beam_start, target, &targets));
+        if still_locked {
+            beam.hold_seconds += time.delta_seconds();
+        } else {
+            beam.locked = tower.choose_target(beam_start, &targets);
+            beam.hold_seconds = 0.0;
+            beam.pending_damage = 0.0;
+        }
+
+        let Some(target) = beam.locked else {
+            continue;
+        };
+        beam.pending_damage +=
+            beam.damage_per_second * beam.multiplier() * tower.buffs.damage * time.delta_seconds();
+        let amount = beam.pending_damage.floor();
+        if amount >= 1.0 {
+            beam.pending_damage -= amount;
+            damage_events.send(DamageEvent {
+                target,
+                amount: amount as i32,
+                damage_type: beam.damage_type,
+                tower_type: Some(*tower_type),
+                knockback: 0.0,
+            });
+        }
+    }
+}
+
+fn update_beam_visuals(
+    towers: Query<(&Tower, &Beam, &Transform, &Children), Without<BeamVisual>>,
+    targets: Query<&Transform, (With<Target>, Without<BeamVisual>)>,
+    mut visuals: Query<
+        (&mut Transform, &mut Visibility, &Handle<StandardMaterial>),
+        With<BeamVisual>,
+    >,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (tower, beam, transform, children) in &towers {
+        for child in children {
+            let Ok((mut visual_transform, mut visibility, material)) = visuals.get_mut(*child)
+            else {
+                continue;
+            };
+
+            let target = beam.locked.and_then(|target| targets.get(target).ok());
+            visibility.is_visible = target.is_some();
+            let Some(target) = target else {
+                continue;
+            };
+
+            // The beam is a child of the tower so everything is in tower space
+            let start = tower.bullet_offset;
+            let end = target.translation - transform.translation;
+            *visual_transform = Transform::from_translation((start + end) / 2.0)
+                .looking_at(end, Vec3::Y)
+                .with_scale(Vec3::new(1.0, 1.0, Vec3::distance(start, end)));
+
+            if let Some(material) = materials.get_mut(material) {
+                let heat = (beam.multiplier() - 1.0) / (beam.max_multiplier - 1.0).max(0.01);
+                material.base_color = Color::ORANGE_RED * (1.0 - heat) + Color::YELLOW * heat;
+            }
+        }
+    }
+}
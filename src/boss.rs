@@ -0,0 +1,211 @@
+use bevy::{ecs::query::QuerySingleError, prelude::*};
+
+use crate::*;
+
+#[derive(Reflect, FromReflect, Clone, Default)]
+pub struct BossPhase {
+    /// The phase starts once health drops to this fraction of the max health
+    pub health_fraction: f32,
+    pub speed_multiplier: f32,
+    pub minions: u32,
+    pub invulnerable_seconds: f32,
+}
+
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Boss {
+    pub phases: Vec<BossPhase>,
+    pub next_phase: usize,
+}
+
+/// Targets with this take no damage until the timer runs out
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Invulnerable {
+    pub timer: Timer,
+}
+
+#[derive(Component)]
+pub struct BossUIRoot;
+
+#[derive(Component)]
+pub struct BossHealthBar;
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Boss>()
+            .register_type::<BossPhase>()
+            .register_type::<Vec<BossPhase>>()
+            .register_type::<Invulnerable>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Gameplay)
+                    .with_system(create_boss_ui)
+                    .with_system(update_boss_ui.after(create_boss_ui)),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Spawn.set().with_system(boss_phases),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Status.set().with_system(tick_invulnerable),
+            );
+    }
+}
+
+fn boss_phases(
+    mut commands: Commands,
+    mut bosses: Query<(Entity, &mut Boss, &mut Target, &Health, &Transform)>,
+    assets: Res<GameAssets>,
+    level: Res<Level>,
+) {
+    for (entity, mut boss, mut target, health, transform) in &mut bosses {
+        while let Some(phase) = boss.phases.get(boss.next_phase).cloned() {
+            if health.value as f32 > phase.health_fraction * health.max as f32 {
+                break;
+            }
+            boss.next_phase += 1;
+
+            target.speed *= phase.speed_multiplier;
+            if phase.invulnerable_seconds > 0.0 {
+                commands.entity(entity).insert(Invulnerable {
+                    timer: Timer::from_seconds(phase.invulnerable_seconds, TimerMode::Once),
+                });
+            }
+            // Minions trail just behind the boss and carry on down the path from there
+            for i in 0..phase.minions {
+                let path_distance = target.path_distance - 0.5 * (i + 1) as f32;
+                let mut position = transform.translation;
+                position.y = EnemyType::Basic.height(&level);
+                spawn_target(
+                    &mut commands,
+                    &assets,
+                    EnemyType::Basic,
+                    position,
+                    target.path_index,
+                    path_distance,
+                );
+            }
+        }
+    }
+}
+
+fn tick_invulnerable(
+    mut commands: Commands,
+    mut targets: Query<(Entity, &mut Invulnerable)>,
+    time: Res<GameTime>,
+) {
+    for (entity, mut invulnerable) in &mut targets {
+        invulnerable.timer.tick(time.delta());
+        if invulnerable.timer.just_finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+fn create_boss_ui(
+    mut commands: Commands,
+    bosses: Query<(), With<Boss>>,
+    root: Query<Entity, With<BossUIRoot>>,
+    asset_server: Res<AssetServer>,
+) {
+    match root.get_single() {
+        Ok(root) => {
+            if bosses.is_empty() {
+                commands.entity(root).despawn_recursive();
+            }
+        }
+        //No root exist
+        Err(QuerySingleError::NoEntities(..)) => {
+            if !bosses.is_empty() {
+                spawn_boss_ui(&mut commands, &asset_server);
+            }
+        }
+        _ => unreachable!("Too many boss ui roots!"),
+    }
+}
+
+fn spawn_boss_ui(commands: &mut Commands, asset_server: &AssetServer) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::FlexStart,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(BossUIRoot)
+        .with_children(|commands| {
+            commands.spawn(TextBundle {
+                style: Style {
+                    margin: UiRect::top(Val::Percent(6.0)),
+                    ..default()
+                },
+                text: Text::from_section(
+                    "BOSS",
+                    TextStyle {
+                        font: asset_server.load("FiraSans-Bold.ttf"),
+                        font_size: 28.0,
+                        color: Color::BLACK,
+                    },
+                ),
+                ..default()
+            });
+            commands
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(50.0), Val::Percent(3.0)),
+                        ..default()
+                    },
+                    background_color: Color::DARK_GRAY.into(),
+                    ..default()
+                })
+                .with_children(|commands| {
+                    commands
+                        .spawn(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            background_color: Color::RED.into(),
+                            ..default()
+                        })
+                        .insert(BossHealthBar);
+                });
+        });
+}
+
+fn update_boss_ui(
+    bosses: Query<(&Health, Option<&Invulnerable>), With<Boss>>,
+    mut bars: Query<(&mut Style, &mut BackgroundColor), With<BossHealthBar>>,
+) {
+    //Several bosses at once share the bar
+    let (health, max_health, invulnerable) = bosses.iter().fold(
+        (0, 0, false),
+        |(health, max_health, invulnerable), (boss_health, boss_invulnerable)| {
+            (
+                health + boss_health.value.max(0),
+                max_health + boss_health.max,
+                invulnerable || boss_invulnerable.is_some(),
+            )
+        },
+    );
+    if max_health == 0 {
+        return;
+    }
+
+    for (mut style, mut color) in &mut bars {
+        style.size.width = Val::Percent(100.0 * health as f32 / max_health as f32);
+        *color = if invulnerable {
+            Color::GOLD.into()
+        } else {
+            Color::RED.into()
+        };
+    }
+}
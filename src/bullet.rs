@@ -5,6 +5,40 @@ use crate::*;
 pub struct Bullet {
     pub direction: Vec3,
     pub speed: f32,
+    pub damage: i32,
+    pub damage_type: DamageType,
+    pub knockback: f32,
+    /// Hits everything within this distance of the impact when above zero
+    pub splash_radius: f32,
+}
+
+/// Lobbed bullets follow an arc to a point on the ground and explode when they land
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Ballistic {
+    pub start: Vec3,
+    pub end: Vec3,
+    /// Height of the arc above the straight line from start to end
+    pub apex_height: f32,
+    pub flight_time: f32,
+    pub elapsed: f32,
+}
+
+impl Ballistic {
+    fn progress(&self) -> f32 {
+        (self.elapsed / self.flight_time).min(1.0)
+    }
+
+    fn position(&self) -> Vec3 {
+        let t = self.progress();
+        self.start.lerp(self.end, t) + Vec3::Y * 4.0 * self.apex_height * t * (1.0 - t)
+    }
+
+    fn velocity(&self) -> Vec3 {
+        let t = self.progress();
+        (self.end - self.start + Vec3::Y * 4.0 * self.apex_height * (1.0 - 2.0 * t))
+            / self.flight_time
+    }
 }
 
 #[derive(Reflect, Component, Default)]
@@ -19,11 +53,19 @@ impl Plugin for BulletPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Bullet>()
             .register_type::<Lifetime>()
-            .add_system_set(
-                SystemSet::on_update(GameState::Gameplay)
-                    .with_system(bullet_collision)
+            .register_type::<Ballistic>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Move
+                    .set()
                     .with_system(move_bullets)
+                    .with_system(move_ballistic_bullets)
                     .with_system(bullet_despawn),
+            )
+            // Checks the whole distance moved this tick so fast bullets can't skip targets
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Collide.set().with_system(bullet_collision),
             );
     }
 }
@@ -31,7 +73,7 @@ impl Plugin for BulletPlugin {
 fn bullet_despawn(
     mut commands: Commands,
     mut bullets: Query<(Entity, &mut Lifetime)>,
-    time: Res<Time>,
+    time: Res<GameTime>,
 ) {
     for (entity, mut lifetime) in &mut bullets {
         lifetime.timer.tick(time.delta());
@@ -41,24 +83,111 @@ fn bullet_despawn(
     }
 }
 
-fn move_bullets(mut bullets: Query<(&Bullet, &mut Transform)>, time: Res<Time>) {
+fn move_bullets(
+    mut bullets: Query<(&Bullet, &mut Transform), Without<Ballistic>>,
+    time: Res<GameTime>,
+) {
     for (bullet, mut transform) in &mut bullets {
         transform.translation += bullet.direction.normalize() * bullet.speed * time.delta_seconds();
     }
 }
 
+#[allow(clippy::type_complexity)]
+fn move_ballistic_bullets(
+    mut commands: Commands,
+    mut bullets: Query<(Entity, &Bullet, &mut Ballistic, &mut Transform, &Parent)>,
+    towers: Query<(&Transform, &TowerType), Without<Bullet>>,
+    targets: Query<(Entity, &Transform, Option<&Flying>), (With<Target>, Without<Bullet>)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    time: Res<GameTime>,
+) {
+    for (entity, bullet, mut ballistic, mut transform, parent) in &mut bullets {
+        ballistic.elapsed += time.delta_seconds();
+        transform.translation = ballistic.position();
+        let velocity = ballistic.velocity();
+        if velocity != Vec3::ZERO {
+            let translation = transform.translation;
+            transform.look_at(translation + velocity, Vec3::Y);
+        }
+
+        if ballistic.elapsed >= ballistic.flight_time {
+            commands.entity(entity).despawn_recursive();
+            let Ok((tower_transform, tower_type)) = towers.get(parent.get()) else {
+                continue;
+            };
+            // Landing on the ground can only hurt what is walking on it
+            let landing = tower_transform.translation + ballistic.end;
+            for (target, target_transform, flying) in &targets {
+                if flying.is_none()
+                    && Vec3::distance(target_transform.translation, landing)
+                        < bullet.splash_radius + 0.4 * target_transform.scale.x
+                {
+                    damage_events.send(bullet.damage_event(target, Some(*tower_type)));
+                }
+            }
+        }
+    }
+}
+
+impl Bullet {
+    fn damage_event(&self, target: Entity, tower_type: Option<TowerType>) -> DamageEvent {
+        DamageEvent {
+            target,
+            amount: self.damage,
+            damage_type: self.damage_type,
+            tower_type,
+            knockback: self.knockback,
+        }
+    }
+}
+
 fn bullet_collision(
     mut commands: Commands,
-    bullets: Query<(Entity, &GlobalTransform), With<Bullet>>,
-    mut targets: Query<(&mut Health, &Transform), With<Target>>,
+    bullets: Query<(Entity, &Bullet, &Transform, &Parent), Without<Ballistic>>,
+    targets: Query<(Entity, &Transform), With<Target>>,
+    towers: Query<(&Transform, &TowerType)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    time: Res<GameTime>,
 ) {
-    for (bullet, bullet_transform) in &bullets {
-        for (mut health, target_transform) in &mut targets {
-            if Vec3::distance(bullet_transform.translation(), target_transform.translation) < 0.4 {
-                commands.entity(bullet).despawn_recursive();
-                health.value -= 1;
+    for (bullet_entity, bullet, bullet_transform, parent) in &bullets {
+        let Ok((tower_transform, tower_type)) = towers.get(parent.get()) else {
+            continue;
+        };
+        // Bullets are in tower space, towers have no parent so their transform is already the world one
+        let end = tower_transform.transform_point(bullet_transform.translation);
+        let start = tower_transform.transform_point(
+            bullet_transform.translation
+                - bullet.direction.normalize() * bullet.speed * time.delta_seconds(),
+        );
+        for (target, target_transform) in &targets {
+            //Bigger targets like bosses are easier to hit
+            let radius = 0.4 * target_transform.scale.x;
+            let impact = closest_on_segment(target_transform.translation, start, end);
+            if Vec3::distance(impact, target_transform.translation) < radius {
+                commands.entity(bullet_entity).despawn_recursive();
+                if bullet.splash_radius > 0.0 {
+                    for (splashed, splashed_transform) in &targets {
+                        if Vec3::distance(impact, splashed_transform.translation)
+                            < bullet.splash_radius
+                        {
+                            damage_events.send(bullet.damage_event(splashed, Some(*tower_type)));
+                        }
+                    }
+                } else {
+                    damage_events.send(bullet.damage_event(target, Some(*tower_type)));
+                }
                 break;
             }
         }
     }
 }
+
+fn closest_on_segment(point: Vec3, start: Vec3, end: Vec3) -> Vec3 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    if length_squared == 0.0 {
+        return start;
+    }
+    let t = ((point - start).dot(segment) / length_squared).clamp(0.0, 1.0);
+    start + segment * t
+}
@@ -0,0 +1,343 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    render::camera::ScalingMode,
+};
+
+use crate::*;
+
+const MIN_DISTANCE: f32 = 3.0;
+const MAX_DISTANCE: f32 = 30.0;
+const MIN_PITCH: f32 = 0.15;
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.05;
+/// Pixels from the edge of the window where the cursor starts panning
+const EDGE_PAN_MARGIN: f32 = 12.0;
+/// Height of the ground shown by the top down view for each unit of distance,
+/// about what the angled view shows so zooming feels the same in both
+const TOP_DOWN_HEIGHT_PER_DISTANCE: f32 = 0.8;
+/// How close focusing on a tower brings the camera, it doesn't zoom out if already closer
+const FOCUS_DISTANCE: f32 = 8.0;
+pub const BOOKMARK_SLOTS: usize = 4;
+/// How quickly the camera catches up with where the controls put it, higher is snappier
+const SMOOTHING: f32 = 12.0;
+
+/// Where the camera orbits and how it looks at that point
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct OrbitPose {
+    /// Point on the ground the camera looks at
+    pub focus: Vec3,
+    /// Angle around the focus, 0 looks down -Z
+    pub yaw: f32,
+    /// Angle above the ground
+    pub pitch: f32,
+    pub distance: f32,
+}
+
+impl OrbitPose {
+    pub fn looking_at(eye: Vec3, focus: Vec3) -> Self {
+        let offset = eye - focus;
+        OrbitPose {
+            focus,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / offset.length()).asin(),
+            distance: offset.length(),
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        let offset = Vec3::new(
+            self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.cos() * self.pitch.cos(),
+        ) * self.distance;
+        Transform::from_translation(self.focus + offset).looking_at(self.focus, Vec3::Y)
+    }
+
+    /// Flat directions for panning, the camera's view and its right side
+    fn ground_axes(&self) -> (Vec3, Vec3) {
+        let forward = Vec3::new(-self.yaw.sin(), 0.0, -self.yaw.cos());
+        let right = Vec3::new(self.yaw.cos(), 0.0, -self.yaw.sin());
+        (forward, right)
+    }
+
+    fn lerp(&self, other: &OrbitPose, t: f32) -> OrbitPose {
+        OrbitPose {
+            focus: self.focus.lerp(other.focus, t),
+            yaw: self.yaw + (other.yaw - self.yaw) * t,
+            pitch: self.pitch + (other.pitch - self.pitch) * t,
+            distance: self.distance + (other.distance - self.distance) * t,
+        }
+    }
+}
+
+#[derive(Reflect, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraView {
+    /// Perspective from above and to the side
+    #[default]
+    Angled,
+    /// Orthographic straight down, the pitch is kept for switching back
+    TopDown,
+}
+
+/// The controls move `target`, the camera follows it through `current`
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct CameraRig {
+    pub target: OrbitPose,
+    pub current: OrbitPose,
+    pub view: CameraView,
+}
+
+/// A view stored in the settings, see `Settings::camera_bookmarks`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct CameraBookmark {
+    pub pose: OrbitPose,
+    pub view: CameraView,
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<CameraRig>()
+            .add_startup_system(spawn_camera)
+            .add_system(camera_controls)
+            .add_system(camera_mouse_controls)
+            .add_system(camera_presets)
+            .add_system(
+                follow_camera_rig
+                    .after(camera_controls)
+                    .after(camera_mouse_controls)
+                    .after(camera_presets),
+            );
+    }
+}
+
+fn spawn_camera(mut commands: Commands) {
+    let pose = OrbitPose::looking_at(Vec3::new(-2.0, 2.5, 5.0), Vec3::ZERO);
+    commands
+        .spawn(Camera3dBundle {
+            transform: pose.transform(),
+            ..default()
+        })
+        .insert(CameraRig {
+            target: pose,
+            current: pose,
+            view: CameraView::Angled,
+        })
+        .insert(PickingCameraBundle::default());
+}
+
+fn camera_controls(
+    input: ActionInput,
+    mut rigs: Query<&mut CameraRig>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+) {
+    let mut rig = rigs.single_mut();
+    let (forward, right) = rig.target.ground_axes();
+
+    //Further out covers more ground per second
+    let speed = 0.5 * rig.target.distance * settings.camera_sensitivity * time.delta_seconds();
+    let rotate_speed = 1.5 * settings.camera_sensitivity * time.delta_seconds();
+    let zoom_speed = 1.5 * settings.camera_sensitivity * time.delta_seconds();
+
+    if input.pressed(InputAction::PanForward) {
+        rig.target.focus += forward * speed;
+    }
+    if input.pressed(InputAction::PanBack) {
+        rig.target.focus -= forward * speed;
+    }
+    if input.pressed(InputAction::PanLeft) {
+        rig.target.focus -= right * speed;
+    }
+    if input.pressed(InputAction::PanRight) {
+        rig.target.focus += right * speed;
+    }
+    if input.pressed(InputAction::RotateLeft) {
+        rig.target.yaw -= rotate_speed;
+    }
+    if input.pressed(InputAction::RotateRight) {
+        rig.target.yaw += rotate_speed;
+    }
+    if input.pressed(InputAction::ZoomIn) {
+        rig.target.distance *= 1.0 - zoom_speed.min(0.5);
+    }
+    if input.pressed(InputAction::ZoomOut) {
+        rig.target.distance *= 1.0 + zoom_speed;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn camera_mouse_controls(
+    input: ActionInput,
+    mut wheel: EventReader<MouseWheel>,
+    mut motion: EventReader<MouseMotion>,
+    windows: Res<Windows>,
+    mut rigs: Query<&mut CameraRig>,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    game_time: Res<GameTime>,
+) {
+    let mut rig = rigs.single_mut();
+    let (forward, right) = rig.target.ground_axes();
+    let sensitivity = settings.camera_sensitivity;
+
+    for event in wheel.iter() {
+        let lines = match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 100.0,
+        };
+        rig.target.distance *= (1.0 - 0.1 * sensitivity).powf(lines);
+    }
+
+    let drag: Vec2 = motion.iter().map(|event| event.delta).sum();
+    if input.pressed(InputAction::DragPan) {
+        //Grabs the ground, so it moves the other way from the mouse
+        let scale = 0.002 * rig.target.distance * sensitivity;
+        rig.target.focus += (forward * drag.y - right * drag.x) * scale;
+    } else if input.pressed(InputAction::DragOrbit) {
+        rig.target.yaw -= drag.x * 0.005 * sensitivity;
+        rig.target.pitch += drag.y * 0.005 * sensitivity;
+    }
+
+    //Not while a menu is up, reaching for its buttons would scroll the map
+    if !game_time.paused {
+        if let Some(window) = windows.get_primary() {
+            if let Some(cursor) = window.cursor_position() {
+                let speed = 0.5 * rig.target.distance * sensitivity * time.delta_seconds();
+                if cursor.x < EDGE_PAN_MARGIN {
+                    rig.target.focus -= right * speed;
+                }
+                if cursor.x > window.width() - EDGE_PAN_MARGIN {
+                    rig.target.focus += right * speed;
+                }
+                //Cursor y starts at the bottom of the window
+                if cursor.y < EDGE_PAN_MARGIN {
+                    rig.target.focus -= forward * speed;
+                }
+                if cursor.y > window.height() - EDGE_PAN_MARGIN {
+                    rig.target.focus += forward * speed;
+                }
+            }
+        }
+    }
+}
+
+/// Towers and the empty bases they can be built on
+type Selectable<'w, 's> =
+    Query<'w, 's, (&'static Selection, &'static Transform), Or<(With<Tower>, With<TowerBase>)>>;
+
+fn camera_presets(
+    input: ActionInput,
+    mut rigs: Query<&mut CameraRig>,
+    selections: Selectable,
+    level: Res<Level>,
+    mut settings: ResMut<Settings>,
+    mut notices: EventWriter<NoticeEvent>,
+) {
+    let mut rig = rigs.single_mut();
+
+    if input.just_pressed(InputAction::ToggleTopDown) {
+        rig.view = match rig.view {
+            CameraView::Angled => CameraView::TopDown,
+            CameraView::TopDown => CameraView::Angled,
+        };
+    }
+
+    if input.just_pressed(InputAction::FocusSelection) {
+        if let Some((_, transform)) = selections
+            .iter()
+            .find(|(selection, _)| selection.selected())
+        {
+            rig.target.focus = transform.translation;
+            rig.target.distance = rig.target.distance.min(FOCUS_DISTANCE);
+        }
+    }
+
+    for slot in 0..BOOKMARK_SLOTS {
+        if !input.just_pressed(InputAction::Bookmark(slot)) {
+            continue;
+        }
+        if input.pressed(InputAction::StoreBookmark) {
+            let bookmark = CameraBookmark {
+                pose: rig.target,
+                view: rig.view,
+            };
+            settings
+                .camera_bookmarks
+                .entry(level.name.clone())
+                .or_default()
+                .insert(slot, bookmark);
+            match settings.save() {
+                Ok(()) => notices.send(NoticeEvent::info(format!(
+                    "Camera bookmark {} saved",
+                    slot + 1
+                ))),
+                Err(err) => {
+                    error!("Failed to save settings: {}", err);
+                    notices.send(NoticeEvent::error(format!("Can't save bookmark: {}", err)));
+                }
+            }
+        } else if let Some(bookmark) = settings
+            .camera_bookmarks
+            .get(&level.name)
+            .and_then(|bookmarks| bookmarks.get(&slot))
+        {
+            rig.target = bookmark.pose;
+            rig.view = bookmark.view;
+        }
+    }
+}
+
+/// Keeps the target inside the level and eases the camera toward it
+fn follow_camera_rig(
+    mut cameras: Query<(&mut CameraRig, &mut Transform, &mut Projection)>,
+    level: Res<Level>,
+    time: Res<Time>,
+) {
+    let (mut rig, mut transform, mut projection) = cameras.single_mut();
+    let extent = level.ground_size / 2.0;
+    let target = &mut rig.target;
+    target.focus.x = target.focus.x.clamp(-extent, extent);
+    target.focus.z = target.focus.z.clamp(-extent, extent);
+    target.focus.y = 0.0;
+    target.pitch = target.pitch.clamp(MIN_PITCH, MAX_PITCH);
+    target.distance = target.distance.clamp(MIN_DISTANCE, MAX_DISTANCE);
+
+    //Top down only overrides the pitch on the way to the camera so switching back restores it
+    let goal = match rig.view {
+        CameraView::Angled => rig.target,
+        CameraView::TopDown => OrbitPose {
+            pitch: MAX_PITCH,
+            ..rig.target
+        },
+    };
+    let t = 1.0 - (-SMOOTHING * time.delta_seconds()).exp();
+    rig.current = rig.current.lerp(&goal, t);
+    *transform = rig.current.transform();
+
+    match rig.view {
+        CameraView::Angled => {
+            if !matches!(*projection, Projection::Perspective(_)) {
+                *projection = Projection::Perspective(default());
+            }
+        }
+        CameraView::TopDown => {
+            let scaling_mode =
+                ScalingMode::FixedVertical(rig.current.distance * TOP_DOWN_HEIGHT_PER_DISTANCE);
+            match projection.as_mut() {
+                Projection::Orthographic(orthographic) => {
+                    orthographic.scaling_mode = scaling_mode;
+                }
+                _ => {
+                    *projection = Projection::Orthographic(OrthographicProjection {
+                        scaling_mode,
+                        ..default()
+                    });
+                }
+            }
+        }
+    }
+}
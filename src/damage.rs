@@ -0,0 +1,129 @@
+use crate::*;
+
+#[derive(Reflect, FromReflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DamageType {
+    #[default]
+    Physical,
+    Acid,
+    Nature,
+}
+
+/// What armor can be immune or weak to, either a kind of damage or everything from one tower type
+#[derive(Reflect, FromReflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageSource {
+    Type(DamageType),
+    Tower(TowerType),
+}
+
+/// Flat reduction applied to every hit, after immunities and weaknesses
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Armor {
+    pub flat: i32,
+    pub immune: Vec<DamageSource>,
+    /// Damage from these is doubled
+    pub weak: Vec<DamageSource>,
+}
+
+/// Every way of hurting a target goes through this event so armor and shields always apply
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: i32,
+    pub damage_type: DamageType,
+    pub tower_type: Option<TowerType>,
+    /// Distance to push the target back along its path
+    pub knockback: f32,
+}
+
+impl Armor {
+    fn matches(sources: &[DamageSource], event: &DamageEvent) -> bool {
+        sources.iter().any(|source| match source {
+            DamageSource::Type(damage_type) => *damage_type == event.damage_type,
+            DamageSource::Tower(tower_type) => Some(*tower_type) == event.tower_type,
+        })
+    }
+}
+
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Armor>()
+            .register_type::<DamageType>()
+            .add_simulation_event::<DamageEvent>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Damage.set().with_system(apply_damage),
+            );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_damage(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut targets: Query<(
+        &mut Health,
+        &Transform,
+        Option<&Armor>,
+        Option<&mut Shield>,
+        Option<&mut Knockback>,
+        Option<&Invulnerable>,
+    )>,
+    asset_server: Res<AssetServer>,
+) {
+    for event in damage_events.iter() {
+        //Target might have died earlier this frame
+        let Ok((mut health, transform, armor, shield, knockback, invulnerable)) =
+            targets.get_mut(event.target)
+        else {
+            continue;
+        };
+        let popup_position = transform.translation + Vec3::Y * 0.5 * transform.scale.y;
+
+        if invulnerable.is_some() {
+            continue;
+        }
+
+        if armor.is_some_and(|armor| Armor::matches(&armor.immune, event)) {
+            spawn_popup(
+                &mut commands,
+                &asset_server,
+                "IMMUNE",
+                Color::WHITE,
+                popup_position,
+            );
+            continue;
+        }
+
+        //Armor can stop the damage but not the push
+        if let Some(mut knockback) = knockback {
+            knockback.remaining = (knockback.remaining + event.knockback).min(knockback.max);
+        }
+
+        let mut amount = event.amount;
+        if let Some(armor) = armor {
+            if Armor::matches(&armor.weak, event) {
+                amount *= 2;
+            }
+            amount -= armor.flat;
+            if amount <= 0 {
+                spawn_popup(
+                    &mut commands,
+                    &asset_server,
+                    "BLOCKED",
+                    Color::GRAY,
+                    popup_position,
+                );
+                continue;
+            }
+        }
+
+        if let Some(mut shield) = shield {
+            let absorbed = amount.min(shield.value.max(0));
+            shield.value -= absorbed;
+            amount -= absorbed;
+        }
+        health.value -= amount;
+    }
+}
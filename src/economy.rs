@@ -0,0 +1,136 @@
+use crate::*;
+
+/// Farm towers don't shoot, they pay out at the end of every wave and a little over time
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Farm {
+    pub timer: Timer,
+}
+
+/// Income goes up with the tower tier
+impl Farm {
+    pub fn income_per_wave(&self, tier: u32) -> u32 {
+        match tier {
+            1 => 20,
+            2 => 35,
+            _ => 55,
+        }
+    }
+
+    pub fn income_per_tick(&self, tier: u32) -> u32 {
+        match tier {
+            1 => 5,
+            2 => 8,
+            _ => 12,
+        }
+    }
+}
+
+/// Running totals for the end of game summary
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct GameStats {
+    pub kills: u32,
+    pub kill_income: u32,
+    pub farm_income: u32,
+    pub waves_cleared: u32,
+}
+
+impl GameStats {
+    pub fn summary(&self) -> String {
+        format!(
+            "Waves cleared: {}\nKills: {}\nKill income: {}\nFarm income: {}",
+            self.waves_cleared, self.kills, self.kill_income, self.farm_income
+        )
+    }
+}
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Farm>()
+            .register_type::<GameStats>()
+            .init_resource::<GameStats>()
+            .add_system_set(SystemSet::on_enter(GameState::Gameplay).with_system(reset_stats))
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Economy
+                    .set()
+                    .with_system(farm_wave_income)
+                    .with_system(farm_timer_income),
+            );
+    }
+}
+
+fn reset_stats(mut stats: ResMut<GameStats>) {
+    *stats = GameStats::default();
+}
+
+fn farm_wave_income(
+    mut commands: Commands,
+    farms: Query<(&Farm, &Tower, &Transform)>,
+    mut player: Query<&mut Player>,
+    mut stats: ResMut<GameStats>,
+    mut cleared_events: EventReader<WaveClearedEvent>,
+    asset_server: Res<AssetServer>,
+) {
+    let mut player = player.single_mut();
+    for _event in cleared_events.iter() {
+        for (farm, tower, transform) in &farms {
+            let income = farm.income_per_wave(tower.tier);
+            pay_farm_income(
+                &mut commands,
+                &asset_server,
+                &mut player,
+                &mut stats,
+                income,
+                transform,
+            );
+        }
+    }
+}
+
+fn farm_timer_income(
+    mut commands: Commands,
+    mut farms: Query<(&mut Farm, &Tower, &Transform)>,
+    mut player: Query<&mut Player>,
+    mut stats: ResMut<GameStats>,
+    asset_server: Res<AssetServer>,
+    time: Res<GameTime>,
+) {
+    let mut player = player.single_mut();
+    for (mut farm, tower, transform) in &mut farms {
+        farm.timer.tick(time.delta());
+        if farm.timer.just_finished() {
+            let income = farm.income_per_tick(tower.tier);
+            pay_farm_income(
+                &mut commands,
+                &asset_server,
+                &mut player,
+                &mut stats,
+                income,
+                transform,
+            );
+        }
+    }
+}
+
+fn pay_farm_income(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    player: &mut Player,
+    stats: &mut GameStats,
+    income: u32,
+    transform: &Transform,
+) {
+    player.money += income;
+    stats.farm_income += income;
+    spawn_popup(
+        commands,
+        asset_server,
+        &format!("+{}", income),
+        Color::GOLD,
+        transform.translation + Vec3::Y * 0.8,
+    );
+}
@@ -0,0 +1,119 @@
+use crate::*;
+
+/// Sent once when the player runs out of health or clears the last wave
+pub struct GameOverEvent {
+    pub won: bool,
+}
+
+#[derive(Component)]
+pub struct GameOverPanel;
+
+#[derive(Component)]
+pub struct MainMenuButton;
+
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<GameOverEvent>().add_system_set(
+            SystemSet::on_update(GameState::Gameplay)
+                .with_system(show_game_over)
+                .with_system(main_menu_button_clicked),
+        );
+    }
+}
+
+fn show_game_over(
+    mut commands: Commands,
+    mut events: EventReader<GameOverEvent>,
+    panels: Query<(), With<GameOverPanel>>,
+    stats: Res<GameStats>,
+    asset_server: Res<AssetServer>,
+) {
+    //Only the first one counts, the game carries on behind the panel
+    let Some(event) = events.iter().next() else {
+        return;
+    };
+    if !panels.is_empty() {
+        return;
+    }
+    info!("{}", stats.summary());
+
+    let font = asset_server.load("FiraSans-Bold.ttf");
+    let title = if event.won { "Victory" } else { "Game Over" };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(30.0),
+                    right: Val::Percent(30.0),
+                    top: Val::Percent(30.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(Val::Px(16.0)),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+            z_index: ZIndex::Global(5),
+            ..default()
+        })
+        .insert(GameOverPanel)
+        .insert(Name::new("GameOverPanel"))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 64.0,
+                    color: if event.won { Color::GOLD } else { Color::RED },
+                },
+            ));
+            commands.spawn(TextBundle::from_section(
+                stats.summary(),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 28.0,
+                    color: Color::WHITE,
+                },
+            ));
+            commands
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(200.0), Val::Px(44.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::top(Val::Px(12.0)),
+                        ..default()
+                    },
+                    background_color: Color::ORANGE.into(),
+                    ..default()
+                })
+                .insert(MainMenuButton)
+                .with_children(|commands| {
+                    commands.spawn(TextBundle::from_section(
+                        "Main Menu",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 28.0,
+                            color: Color::BLACK,
+                        },
+                    ));
+                });
+        });
+}
+
+fn main_menu_button_clicked(
+    interactions: Query<&Interaction, (With<MainMenuButton>, Changed<Interaction>)>,
+    mut game_state: ResMut<State<GameState>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+) {
+    for interaction in &interactions {
+        if matches!(interaction, Interaction::Clicked) {
+            game_state.set(GameState::MainMenu).unwrap();
+            mouse_input.clear();
+        }
+    }
+}
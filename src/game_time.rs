@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use bevy::ecs::schedule::ShouldRun;
+
+use crate::*;
+
+/// Length of one simulation tick, every gameplay system steps by exactly this much
+pub const TICK_SECONDS: f64 = 1.0 / 60.0;
+
+/// Catching up after a long frame is capped so a hitch can't snowball
+const MAX_TICKS_PER_FRAME: f64 = 12.0;
+
+/// Ticks run per frame at most while seeking, high enough to skip a whole wave in a few frames
+const MAX_SEEK_TICKS_PER_FRAME: u32 = 600;
+
+/// Clock for the gameplay simulation, which runs in fixed ticks.
+/// Fast forward runs more ticks per frame instead of longer ones.
+/// Anything the player looks at or controls outside the simulation (UI, camera) keeps using `Time`.
+#[derive(Resource)]
+pub struct GameTime {
+    pub speed: f32,
+    /// Ticks run since gameplay started
+    pub tick: u64,
+    /// Runs ticks as fast as possible until this one, used to jump around in replays
+    pub seek_to: Option<u64>,
+    /// Stops the simulation, while a menu is open over the game
+    pub paused: bool,
+    accumulated: f64,
+    ticks_this_frame: u32,
+}
+
+impl Default for GameTime {
+    fn default() -> Self {
+        GameTime {
+            speed: 1.0,
+            tick: 0,
+            seek_to: None,
+            paused: false,
+            accumulated: 0.0,
+            ticks_this_frame: 0,
+        }
+    }
+}
+
+impl GameTime {
+    pub fn delta(&self) -> Duration {
+        Duration::from_secs_f64(TICK_SECONDS)
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        TICK_SECONDS as f32
+    }
+}
+
+#[derive(Component)]
+pub struct GameSpeedButton(pub f32);
+
+pub struct GameTimePlugin;
+
+impl Plugin for GameTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameTime>()
+            .add_system_to_stage(CoreStage::PreUpdate, accumulate_game_time)
+            .add_system_set(
+                SystemSet::on_enter(GameState::Gameplay)
+                    .with_system(reset_game_speed)
+                    .with_system(spawn_speed_buttons),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Gameplay)
+                    .with_system(game_speed_hotkeys)
+                    .with_system(game_speed_buttons)
+                    .with_system(update_speed_buttons),
+            );
+    }
+}
+
+pub const SPEEDS: [f32; 3] = [1.0, 2.0, 3.0];
+
+fn accumulate_game_time(mut game_time: ResMut<GameTime>, time: Res<Time>) {
+    game_time.ticks_this_frame = 0;
+    game_time.accumulated = (game_time.accumulated
+        + time.delta_seconds_f64() * game_time.speed as f64)
+        .min(MAX_TICKS_PER_FRAME * TICK_SECONDS);
+}
+
+/// Run criteria for the simulation stage, runs it once for every whole tick of time banked
+pub fn run_simulation_ticks(
+    mut game_time: ResMut<GameTime>,
+    state: Res<State<GameState>>,
+) -> ShouldRun {
+    if *state.current() != GameState::Gameplay || game_time.paused {
+        game_time.accumulated = 0.0;
+        return ShouldRun::No;
+    }
+    if let Some(seek_to) = game_time.seek_to {
+        if game_time.tick >= seek_to {
+            game_time.seek_to = None;
+            game_time.accumulated = 0.0;
+        } else if game_time.ticks_this_frame < MAX_SEEK_TICKS_PER_FRAME {
+            game_time.ticks_this_frame += 1;
+            game_time.tick += 1;
+            return ShouldRun::YesAndCheckAgain;
+        } else {
+            return ShouldRun::No;
+        }
+    }
+    if game_time.accumulated >= TICK_SECONDS {
+        game_time.accumulated -= TICK_SECONDS;
+        game_time.tick += 1;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::No
+    }
+}
+
+fn reset_game_speed(mut game_time: ResMut<GameTime>) {
+    //A replay seek that restarted the game keeps going after the restart
+    *game_time = GameTime {
+        seek_to: game_time.seek_to,
+        ..default()
+    };
+}
+
+fn game_speed_hotkeys(input: ActionInput, mut game_time: ResMut<GameTime>) {
+    for (index, speed) in SPEEDS.into_iter().enumerate() {
+        if input.just_pressed(InputAction::GameSpeed(index)) {
+            game_time.speed = speed;
+        }
+    }
+    if input.just_pressed(InputAction::SpeedUp) {
+        let next = SPEEDS
+            .iter()
+            .position(|speed| *speed == game_time.speed)
+            .map_or(0, |index| (index + 1) % SPEEDS.len());
+        game_time.speed = SPEEDS[next];
+    }
+}
+
+fn game_speed_buttons(
+    interactions: Query<(&Interaction, &GameSpeedButton), Changed<Interaction>>,
+    mut game_time: ResMut<GameTime>,
+) {
+    for (interaction, button) in &interactions {
+        if matches!(interaction, Interaction::Clicked) {
+            game_time.speed = button.0;
+        }
+    }
+}
+
+fn spawn_speed_buttons(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let text_style = TextStyle {
+        font: asset_server.load("FiraSans-Bold.ttf"),
+        font_size: 20.0,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(1.0),
+                    top: Val::Percent(9.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Name::new("SpeedButtons"))
+        .with_children(|commands| {
+            for speed in SPEEDS {
+                commands
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(40.0), Val::Px(30.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            margin: UiRect::right(Val::Px(4.0)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .insert(GameSpeedButton(speed))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            format!("{}x", speed),
+                            text_style.clone(),
+                        ));
+                    });
+            }
+        });
+}
+
+fn update_speed_buttons(
+    game_time: Res<GameTime>,
+    mut buttons: Query<(&GameSpeedButton, &mut BackgroundColor)>,
+) {
+    for (button, mut color) in &mut buttons {
+        *color = if button.0 == game_time.speed {
+            Color::GOLD.into()
+        } else {
+            Color::WHITE.into()
+        };
+    }
+}
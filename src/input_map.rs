@@ -0,0 +1,614 @@
+use std::{collections::BTreeMap, marker::PhantomData};
+
+use bevy::{ecs::system::SystemParam, input::InputSystem, ui::FocusPolicy};
+
+use crate::*;
+
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            //Before anything reads the input so the new binding doesn't also trigger its old action
+            .add_system_to_stage(CoreStage::PreUpdate, capture_rebinding.after(InputSystem))
+            .add_system(controls_buttons_clicked)
+            .add_system(update_binding_labels);
+    }
+}
+
+/// Everything the player can do with a key, mouse button or gamepad button.
+/// Clicking on buttons and towers still goes through the UI and picking.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InputAction {
+    PanForward,
+    PanBack,
+    PanLeft,
+    PanRight,
+    RotateLeft,
+    RotateRight,
+    ZoomIn,
+    ZoomOut,
+    /// Held while moving the mouse
+    DragPan,
+    DragOrbit,
+    /// Switches between the angled view and the top down map view
+    ToggleTopDown,
+    /// Moves the camera to the selected tower or base
+    FocusSelection,
+    /// Jumps to bookmark `n`, or stores the current view there while `StoreBookmark` is held
+    Bookmark(usize),
+    StoreBookmark,
+    Pause,
+    /// Builds `TowerType::ALL[n]` on the selected tower base
+    BuildTower(usize),
+    Sell,
+    Upgrade,
+    UseAbility,
+    CallWave,
+    /// Goes through the game speeds, back to 1x after the fastest
+    SpeedUp,
+    /// Sets `SPEEDS[n]` directly
+    GameSpeed(usize),
+    CancelSpell,
+    QuickSave,
+    QuickLoad,
+}
+
+impl InputAction {
+    pub fn all() -> Vec<InputAction> {
+        let mut actions = vec![
+            InputAction::PanForward,
+            InputAction::PanBack,
+            InputAction::PanLeft,
+            InputAction::PanRight,
+            InputAction::RotateLeft,
+            InputAction::RotateRight,
+            InputAction::ZoomIn,
+            InputAction::ZoomOut,
+            InputAction::DragPan,
+            InputAction::DragOrbit,
+            InputAction::ToggleTopDown,
+            InputAction::FocusSelection,
+        ];
+        actions.extend((0..BOOKMARK_SLOTS).map(InputAction::Bookmark));
+        actions.extend([InputAction::StoreBookmark, InputAction::Pause]);
+        actions.extend((0..TowerType::ALL.len()).map(InputAction::BuildTower));
+        actions.extend([
+            InputAction::Sell,
+            InputAction::Upgrade,
+            InputAction::UseAbility,
+            InputAction::CallWave,
+            InputAction::SpeedUp,
+        ]);
+        actions.extend((0..SPEEDS.len()).map(InputAction::GameSpeed));
+        actions.extend([
+            InputAction::CancelSpell,
+            InputAction::QuickSave,
+            InputAction::QuickLoad,
+        ]);
+        actions
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            InputAction::BuildTower(index) => match TowerType::ALL.get(*index) {
+                Some(tower_type) => format!("Build {:?}", tower_type),
+                None => format!("Build tower {}", index + 1),
+            },
+            InputAction::Bookmark(index) => format!("Bookmark {}", index + 1),
+            InputAction::GameSpeed(index) => match SPEEDS.get(*index) {
+                Some(speed) => format!("Speed {}x", speed),
+                None => format!("Speed {}", index + 1),
+            },
+            _ => format!("{:?}", self),
+        }
+    }
+
+    /// The gamepad drives the camera and the actions on the selected tower next to the mouse,
+    /// it can't replace it: selecting, the build and spell buttons and the settings and controls
+    /// screens are mouse only. Start pauses and Select changes the speed, there's no gamepad
+    /// navigation inside the menus.
+    fn default_bindings(&self) -> Vec<Binding> {
+        use GamepadButtonType as Pad;
+        match self {
+            InputAction::PanForward => {
+                vec![Binding::Key(KeyCode::W), Binding::Gamepad(Pad::DPadUp)]
+            }
+            InputAction::PanBack => vec![Binding::Key(KeyCode::S), Binding::Gamepad(Pad::DPadDown)],
+            InputAction::PanLeft => vec![Binding::Key(KeyCode::A), Binding::Gamepad(Pad::DPadLeft)],
+            InputAction::PanRight => {
+                vec![Binding::Key(KeyCode::D), Binding::Gamepad(Pad::DPadRight)]
+            }
+            InputAction::RotateLeft => {
+                vec![Binding::Key(KeyCode::Q), Binding::Gamepad(Pad::LeftTrigger)]
+            }
+            InputAction::RotateRight => {
+                vec![
+                    Binding::Key(KeyCode::E),
+                    Binding::Gamepad(Pad::RightTrigger),
+                ]
+            }
+            InputAction::ZoomIn => vec![
+                Binding::Key(KeyCode::R),
+                Binding::Gamepad(Pad::RightTrigger2),
+            ],
+            InputAction::ZoomOut => vec![
+                Binding::Key(KeyCode::T),
+                Binding::Gamepad(Pad::LeftTrigger2),
+            ],
+            InputAction::DragPan => vec![Binding::Mouse(MouseButton::Middle)],
+            InputAction::DragOrbit => vec![Binding::Mouse(MouseButton::Right)],
+            InputAction::ToggleTopDown => {
+                vec![Binding::Key(KeyCode::V), Binding::Gamepad(Pad::LeftThumb)]
+            }
+            InputAction::FocusSelection => {
+                vec![
+                    Binding::Key(KeyCode::Space),
+                    Binding::Gamepad(Pad::RightThumb),
+                ]
+            }
+            InputAction::Bookmark(index) => [KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4]
+                .get(*index)
+                .map(|key| vec![Binding::Key(*key)])
+                .unwrap_or_default(),
+            InputAction::StoreBookmark => vec![Binding::Key(KeyCode::LControl)],
+            InputAction::Pause => vec![Binding::Key(KeyCode::P), Binding::Gamepad(Pad::Start)],
+            InputAction::BuildTower(index) => [
+                KeyCode::Numpad1,
+                KeyCode::Numpad2,
+                KeyCode::Numpad3,
+                KeyCode::Numpad4,
+                KeyCode::Numpad5,
+                KeyCode::Numpad6,
+            ]
+            .get(*index)
+            .map(|key| vec![Binding::Key(*key)])
+            .unwrap_or_default(),
+            InputAction::Sell => vec![Binding::Key(KeyCode::Delete)],
+            InputAction::Upgrade => vec![Binding::Key(KeyCode::U), Binding::Gamepad(Pad::South)],
+            InputAction::UseAbility => vec![Binding::Key(KeyCode::F), Binding::Gamepad(Pad::West)],
+            InputAction::CallWave => vec![Binding::Key(KeyCode::N), Binding::Gamepad(Pad::North)],
+            InputAction::SpeedUp => vec![Binding::Key(KeyCode::Tab), Binding::Gamepad(Pad::Select)],
+            InputAction::GameSpeed(index) => [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3]
+                .get(*index)
+                .map(|key| vec![Binding::Key(*key)])
+                .unwrap_or_default(),
+            //Not right click, that starts orbiting the camera which is often how a target gets found
+            InputAction::CancelSpell => {
+                vec![Binding::Key(KeyCode::Escape), Binding::Gamepad(Pad::East)]
+            }
+            InputAction::QuickSave => vec![Binding::Key(KeyCode::F5)],
+            InputAction::QuickLoad => vec![Binding::Key(KeyCode::F9)],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Any connected gamepad
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    /// Keys, mouse buttons and gamepad buttons each get their own binding per action
+    fn same_device(&self, other: &Binding) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("Mouse {:?}", button),
+            Binding::Gamepad(button) => format!("Pad {:?}", button),
+        }
+    }
+}
+
+/// Bindings the player has changed, every other action keeps its defaults.
+/// Saved with the settings.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct InputMap {
+    pub bindings: BTreeMap<InputAction, Vec<Binding>>,
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: InputAction) -> Vec<Binding> {
+        self.bindings
+            .get(&action)
+            .cloned()
+            .unwrap_or_else(|| action.default_bindings())
+    }
+
+    /// Replaces the action's binding on the same device, so rebinding a key keeps the gamepad button.
+    /// A binding only does one thing, returns the actions it was taken from.
+    pub fn rebind(&mut self, action: InputAction, binding: Binding) -> Vec<InputAction> {
+        let mut unbound = Vec::new();
+        for other in InputAction::all() {
+            let mut bindings = self.bindings(other);
+            if other == action || !bindings.contains(&binding) {
+                continue;
+            }
+            bindings.retain(|old| *old != binding);
+            self.bindings.insert(other, bindings);
+            unbound.push(other);
+        }
+
+        let mut bindings = self.bindings(action);
+        bindings.retain(|old| !old.same_device(&binding));
+        bindings.push(binding);
+        self.bindings.insert(action, bindings);
+        unbound
+    }
+
+    pub fn label(&self, action: InputAction) -> String {
+        let bindings = self.bindings(action);
+        if bindings.is_empty() {
+            return "-".to_string();
+        }
+        bindings
+            .iter()
+            .map(|binding| binding.label())
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+}
+
+/// Reads actions through the player's bindings, use this instead of `Input<KeyCode>`
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    settings: Res<'w, Settings>,
+    keyboard: Res<'w, Input<KeyCode>>,
+    mouse: Res<'w, Input<MouseButton>>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    gamepads: Res<'w, Gamepads>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> ActionInput<'w, 's> {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.bindings(action).any(|binding| match binding {
+            Binding::Key(key) => self.keyboard.pressed(key),
+            Binding::Mouse(button) => self.mouse.pressed(button),
+            Binding::Gamepad(button) => self
+                .on_every_gamepad(button)
+                .any(|button| self.gamepad_buttons.pressed(button)),
+        })
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.bindings(action).any(|binding| match binding {
+            Binding::Key(key) => self.keyboard.just_pressed(key),
+            Binding::Mouse(button) => self.mouse.just_pressed(button),
+            Binding::Gamepad(button) => self
+                .on_every_gamepad(button)
+                .any(|button| self.gamepad_buttons.just_pressed(button)),
+        })
+    }
+
+    fn bindings(&self, action: InputAction) -> impl Iterator<Item = Binding> {
+        self.settings.input_map.bindings(action).into_iter()
+    }
+
+    fn on_every_gamepad(
+        &self,
+        button_type: GamepadButtonType,
+    ) -> impl Iterator<Item = GamepadButton> + '_ {
+        self.gamepads
+            .iter()
+            .map(move |gamepad| GamepadButton::new(gamepad, button_type))
+    }
+}
+
+/// The action waiting for the player to press its new key, if any
+#[derive(Resource, Default)]
+pub struct Rebinding {
+    pub action: Option<InputAction>,
+}
+
+#[derive(Component)]
+pub struct ControlsMenu;
+
+#[derive(Component)]
+pub enum ControlsButton {
+    Rebind(InputAction),
+    ResetDefaults,
+    Back,
+}
+
+#[derive(Component)]
+pub struct BindingLabel(pub InputAction);
+
+/// Opened from the settings screen, on top of it
+pub fn spawn_controls_menu(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    settings: &Settings,
+) {
+    let text_style = TextStyle {
+        font: asset_server.load("FiraSans-Bold.ttf"),
+        font_size: 18.0,
+        color: Color::WHITE,
+    };
+    let button_text_style = TextStyle {
+        color: Color::BLACK,
+        ..text_style.clone()
+    };
+    let button_style = Style {
+        size: Size::new(Val::Px(50.0), Val::Px(22.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        margin: UiRect::all(Val::Px(2.0)),
+        ..default()
+    };
+    let actions = InputAction::all();
+    let (left, right) = actions.split_at(actions.len().div_ceil(2));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.9).into(),
+            z_index: ZIndex::Global(11),
+            ..default()
+        })
+        // Keeps clicks from reaching the settings screen underneath
+        .insert(Interaction::default())
+        .insert(FocusPolicy::Block)
+        .insert(ControlsMenu)
+        .insert(Name::new("ControlsMenu"))
+        .with_children(|commands| {
+            commands
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|commands| {
+                    for column in [left, right] {
+                        commands
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Column,
+                                    margin: UiRect::horizontal(Val::Px(12.0)),
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|commands| {
+                                for action in column {
+                                    spawn_binding_row(
+                                        commands,
+                                        *action,
+                                        settings,
+                                        &text_style,
+                                        &button_style,
+                                        &button_text_style,
+                                    );
+                                }
+                            });
+                    }
+                });
+            commands
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        margin: UiRect::top(Val::Px(20.0)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|commands| {
+                    for (button, label, color) in [
+                        (ControlsButton::ResetDefaults, "Reset defaults", Color::GRAY),
+                        (ControlsButton::Back, "Back", Color::ORANGE),
+                    ] {
+                        commands
+                            .spawn(ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(160.0), Val::Px(40.0)),
+                                    margin: UiRect::horizontal(Val::Px(8.0)),
+                                    ..button_style.clone()
+                                },
+                                background_color: color.into(),
+                                ..default()
+                            })
+                            .insert(button)
+                            .with_children(|commands| {
+                                commands.spawn(TextBundle::from_section(
+                                    label,
+                                    button_text_style.clone(),
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+fn spawn_binding_row(
+    commands: &mut ChildBuilder,
+    action: InputAction,
+    settings: &Settings,
+    text_style: &TextStyle,
+    button_style: &Style,
+    button_text_style: &TextStyle,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|commands| {
+            commands.spawn(TextBundle {
+                style: Style {
+                    size: Size::new(Val::Px(140.0), Val::Auto),
+                    ..default()
+                },
+                text: Text::from_section(action.name(), text_style.clone()),
+                ..default()
+            });
+            commands
+                .spawn(TextBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(230.0), Val::Auto),
+                        ..default()
+                    },
+                    text: Text::from_section(settings.input_map.label(action), text_style.clone()),
+                    ..default()
+                })
+                .insert(BindingLabel(action));
+            commands
+                .spawn(ButtonBundle {
+                    style: button_style.clone(),
+                    ..default()
+                })
+                .insert(ControlsButton::Rebind(action))
+                .with_children(|commands| {
+                    commands.spawn(TextBundle::from_section("Set", button_text_style.clone()));
+                });
+        });
+}
+
+fn controls_buttons_clicked(
+    mut commands: Commands,
+    interactions: Query<(&Interaction, &ControlsButton), Changed<Interaction>>,
+    menus: Query<Entity, With<ControlsMenu>>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for (interaction, button) in &interactions {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+        match button {
+            ControlsButton::Rebind(action) => rebinding.action = Some(*action),
+            ControlsButton::ResetDefaults => {
+                settings.input_map = InputMap::default();
+                rebinding.action = None;
+            }
+            //Saved along with the rest when the settings screen closes
+            ControlsButton::Back => {
+                for menu in &menus {
+                    commands.entity(menu).despawn_recursive();
+                }
+                rebinding.action = None;
+            }
+        }
+    }
+}
+
+/// Binds the next key, mouse button or gamepad button pressed, Escape cancels
+fn capture_rebinding(
+    mut rebinding: ResMut<Rebinding>,
+    menus: Query<(), With<ControlsMenu>>,
+    mut settings: ResMut<Settings>,
+    mut keyboard: ResMut<Input<KeyCode>>,
+    mut mouse: ResMut<Input<MouseButton>>,
+    mut gamepad_buttons: ResMut<Input<GamepadButton>>,
+    mut notices: EventWriter<NoticeEvent>,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+    //Menu was closed some other way, like the pause key
+    if menus.is_empty() {
+        rebinding.action = None;
+        return;
+    }
+    let key = keyboard.get_just_pressed().next().copied();
+    let mouse_button = mouse.get_just_pressed().next().copied();
+    let gamepad_button = gamepad_buttons.get_just_pressed().next().copied();
+    let pressed = if let Some(key) = key {
+        keyboard.clear_just_pressed(key);
+        if key == KeyCode::Escape {
+            rebinding.action = None;
+            return;
+        }
+        Binding::Key(key)
+    } else if let Some(button) = mouse_button {
+        mouse.clear_just_pressed(button);
+        Binding::Mouse(button)
+    } else if let Some(button) = gamepad_button {
+        gamepad_buttons.clear_just_pressed(button);
+        Binding::Gamepad(button.button_type)
+    } else {
+        return;
+    };
+    for unbound in settings.input_map.rebind(action, pressed) {
+        notices.send(NoticeEvent::info(format!(
+            "{} moved from {} to {}",
+            pressed.label(),
+            unbound.name(),
+            action.name()
+        )));
+    }
+    rebinding.action = None;
+}
+
+fn update_binding_labels(
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    mut labels: Query<(&BindingLabel, &mut Text)>,
+) {
+    if !settings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (label, mut text) in &mut labels {
+        text.sections[0].value = if rebinding.action == Some(label.0) {
+            "Press a key, Esc to cancel".to_string()
+        } else {
+            settings.input_map.label(label.0)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_are_unique() {
+        let map = InputMap::default();
+        let mut seen = Vec::new();
+        for action in InputAction::all() {
+            for binding in map.bindings(action) {
+                assert!(
+                    !seen.contains(&binding),
+                    "{} is bound twice",
+                    binding.label()
+                );
+                seen.push(binding);
+            }
+        }
+    }
+
+    #[test]
+    fn rebind_takes_binding_from_other_action() {
+        let mut map = InputMap::default();
+        let unbound = map.rebind(InputAction::Sell, Binding::Key(KeyCode::U));
+        assert_eq!(unbound, vec![InputAction::Upgrade]);
+        assert_eq!(
+            map.bindings(InputAction::Sell),
+            vec![Binding::Key(KeyCode::U)]
+        );
+        //The gamepad button stays
+        assert_eq!(
+            map.bindings(InputAction::Upgrade),
+            vec![Binding::Gamepad(GamepadButtonType::South)]
+        );
+    }
+}
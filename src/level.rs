@@ -0,0 +1,266 @@
+use bevy::{
+    math::Vec3Swizzles,
+    pbr::NotShadowCaster,
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+
+use crate::*;
+
+//Could be loaded from a config or level file
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct Level {
+    /// Shown to the player and used to keep per level preferences apart
+    #[serde(default = "default_level_name")]
+    pub name: String,
+    pub ground_size: f32,
+    /// The first waypoint is where targets spawn and the last is where they exit
+    pub waypoints: Vec<Vec2>,
+    pub path_mode: PathMode,
+    pub path_width: f32,
+    /// Route for flying targets, empty means a straight line from spawn to exit
+    pub air_waypoints: Vec<Vec2>,
+    pub air_altitude: f32,
+    pub tower_sites: Vec<Vec3>,
+    pub decoration_count: u32,
+    pub decoration_seed: u32,
+    /// Seeds the random numbers used during gameplay
+    pub simulation_seed: u64,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        let mut tower_sites = Vec::new();
+        for i in 0..10 {
+            for j in 0..2 {
+                tower_sites.push(Vec3::new(2.0 * i as f32 + j as f32, 0.8, 5.0 * j as f32));
+            }
+        }
+
+        Level {
+            name: default_level_name(),
+            ground_size: 50.0,
+            waypoints: vec![
+                Vec2::new(0.0, 2.5),
+                Vec2::new(6.0, 2.0),
+                Vec2::new(6.0, 6.0),
+                Vec2::new(9.0, 9.0),
+            ],
+            //PathMode::Linear walks the waypoints in straight lines instead
+            path_mode: PathMode::CatmullRom,
+            path_width: 0.9,
+            air_waypoints: Vec::new(),
+            air_altitude: 2.0,
+            tower_sites,
+            decoration_count: 80,
+            decoration_seed: 7,
+            simulation_seed: 1234,
+        }
+    }
+}
+
+//Replays from before levels had names were all on this one
+fn default_level_name() -> String {
+    "Meadow".to_string()
+}
+
+impl Level {
+    /// Paths for ground and flying targets, these have to be replaced whenever the level is
+    pub fn paths(&self) -> (TargetPath, AirPath) {
+        let air_waypoints = if self.air_waypoints.is_empty() {
+            vec![self.waypoints[0], self.waypoints[self.waypoints.len() - 1]]
+        } else {
+            self.air_waypoints.clone()
+        };
+        (
+            TargetPath::new(self.waypoints.clone(), self.path_mode),
+            AirPath {
+                path: TargetPath::new(air_waypoints, self.path_mode),
+            },
+        )
+    }
+}
+
+#[derive(Component)]
+pub struct LevelGeometry;
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        let level = Level::default();
+        let (path, air_path) = level.paths();
+        app.insert_resource(path)
+            .insert_resource(air_path)
+            .insert_resource(level)
+            .add_system_set(SystemSet::on_enter(GameState::Gameplay).with_system(spawn_level));
+    }
+}
+
+fn spawn_level(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    level: Res<Level>,
+    path: Res<TargetPath>,
+) {
+    commands
+        .spawn(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Plane {
+                size: level.ground_size,
+            })),
+            material: materials.add(Color::rgb(0.3, 0.5, 0.3).into()),
+            ..default()
+        })
+        .insert(LevelGeometry)
+        .insert(Name::new("Ground"));
+
+    commands
+        .spawn(PbrBundle {
+            mesh: meshes.add(path_mesh(&path, level.path_width)),
+            material: materials.add(Color::rgb(0.55, 0.45, 0.3).into()),
+            // Slightly above the ground to avoid z-fighting
+            transform: Transform::from_xyz(0.0, 0.01, 0.0),
+            ..default()
+        })
+        .insert(NotShadowCaster)
+        .insert(LevelGeometry)
+        .insert(Name::new("Path"));
+
+    let markers = [
+        ("Spawn_Marker", path.start(), Color::rgb(0.2, 0.4, 0.9)),
+        ("Exit_Marker", path.end(), Color::rgb(0.9, 0.2, 0.2)),
+    ];
+    for (name, position, color) in markers {
+        let material = materials.add(color.into());
+        commands
+            .spawn(SpatialBundle::from_transform(Transform::from_translation(
+                position.extend(0.0).xzy(),
+            )))
+            .insert(LevelGeometry)
+            .insert(Name::new(name))
+            .with_children(|commands| {
+                commands.spawn(PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Torus {
+                        radius: level.path_width * 0.6,
+                        ring_radius: 0.05,
+                        ..default()
+                    })),
+                    material: material.clone(),
+                    ..default()
+                });
+                commands.spawn(PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Box::new(0.1, 1.2, 0.1))),
+                    material,
+                    transform: Transform::from_xyz(0.0, 0.6, 0.0),
+                    ..default()
+                });
+            });
+    }
+
+    spawn_decorations(&mut commands, &mut meshes, &mut materials, &level, &path);
+}
+
+fn spawn_decorations(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    level: &Level,
+    path: &TargetPath,
+) {
+    let rock_mesh = meshes.add(Mesh::from(shape::Icosphere {
+        radius: 0.25,
+        subdivisions: 1,
+    }));
+    let rock_material = materials.add(Color::rgb(0.45, 0.45, 0.45).into());
+    let bush_mesh = meshes.add(Mesh::from(shape::Icosphere {
+        radius: 0.35,
+        subdivisions: 2,
+    }));
+    let bush_material = materials.add(Color::rgb(0.2, 0.4, 0.15).into());
+
+    let extent = level.ground_size / 2.0 - 1.0;
+    let clearance = level.path_width / 2.0 + 0.6;
+
+    for i in 0..level.decoration_count {
+        let position = Vec2::new(
+            (scatter_random(level.decoration_seed, 3 * i) * 2.0 - 1.0) * extent,
+            (scatter_random(level.decoration_seed, 3 * i + 1) * 2.0 - 1.0) * extent,
+        );
+
+        // Keep the path and every place a tower can go clear
+        let blocked = path.distance_to(position) < clearance
+            || level
+                .tower_sites
+                .iter()
+                .any(|site| Vec2::distance(site.xz(), position) < 1.0);
+        if blocked {
+            continue;
+        }
+
+        let (mesh, material, name) = if scatter_random(level.decoration_seed, 3 * i + 2) < 0.5 {
+            (rock_mesh.clone(), rock_material.clone(), "Rock")
+        } else {
+            (bush_mesh.clone(), bush_material.clone(), "Bush")
+        };
+        commands
+            .spawn(PbrBundle {
+                mesh,
+                material,
+                transform: Transform::from_translation(position.extend(0.1).xzy()),
+                ..default()
+            })
+            .insert(LevelGeometry)
+            .insert(Name::new(name));
+    }
+}
+
+/// Flat strip following the path so players can see where targets will walk
+fn path_mesh(path: &TargetPath, width: f32) -> Mesh {
+    let samples = path.samples();
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+
+    let mut distance = 0.0;
+    for i in 0..samples.len() {
+        let previous = samples[i.saturating_sub(1)];
+        let next = samples[(i + 1).min(samples.len() - 1)];
+        let direction = (next - previous).normalize_or_zero();
+        let right = Vec2::new(-direction.y, direction.x) * width / 2.0;
+
+        if i > 0 {
+            distance += Vec2::distance(samples[i - 1], samples[i]);
+        }
+
+        for (side, u) in [(-right, 0.0), (right, 1.0)] {
+            positions.push((samples[i] + side).extend(0.0).xzy().to_array());
+            normals.push([0.0, 1.0, 0.0]);
+            uvs.push([u, distance / width]);
+        }
+
+        if i + 1 < samples.len() {
+            let left = 2 * i as u32;
+            indices.extend([left, left + 1, left + 2, left + 1, left + 3, left + 2]);
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+// Cheap integer hash so the same seed always scatters decorations the same way
+fn scatter_random(seed: u32, index: u32) -> f32 {
+    let mut x = seed ^ index.wrapping_mul(0x9E37_79B9);
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846C_A68B);
+    x ^= x >> 16;
+    x as f32 / u32::MAX as f32
+}
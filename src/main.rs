@@ -1,6 +1,7 @@
-use bevy::{pbr::NotShadowCaster, prelude::*, utils::FloatOrd};
+use bevy::{prelude::*, utils::FloatOrd};
 use bevy_inspector_egui::{Inspectable, RegisterInspectable, WorldInspectorPlugin};
 use bevy_mod_picking::*;
+use serde::{Deserialize, Serialize};
 
 pub const HEIGHT: f32 = 720.0;
 pub const WIDTH: f32 = 1280.0;
@@ -17,17 +18,63 @@ pub struct GameAssets {
     target_scene: Handle<Scene>,
 }
 
+mod ability;
+mod action;
+mod aura;
+mod beam;
+mod boss;
 mod bullet;
+mod camera;
+mod damage;
+mod economy;
+mod game_over;
+mod game_time;
+mod input_map;
+mod level;
 mod main_menu;
 mod player;
+mod popup;
+mod replay;
+mod save;
+mod settings;
+mod simulation;
+mod spell;
+mod stealth;
+mod support;
 mod target;
 mod tower;
-
+mod tower_panel;
+mod versioned;
+mod wave;
+
+pub use ability::*;
+pub use action::*;
+pub use aura::*;
+pub use beam::*;
+pub use boss::*;
 pub use bullet::*;
+pub use camera::*;
+pub use damage::*;
+pub use economy::*;
+pub use game_over::*;
+pub use game_time::*;
+pub use input_map::*;
+pub use level::*;
 pub use main_menu::*;
 pub use player::*;
+pub use popup::*;
+pub use replay::*;
+pub use save::*;
+pub use settings::*;
+pub use simulation::*;
+pub use spell::*;
+pub use stealth::*;
+pub use support::*;
 pub use target::*;
 pub use tower::*;
+pub use tower_panel::*;
+pub use versioned::*;
+pub use wave::*;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum GameState {
@@ -36,17 +83,15 @@ pub enum GameState {
 }
 
 fn main() {
+    // Read before anything else so the window opens with the right size
+    let settings = Settings::load();
+    let window = settings.clone().unwrap_or_default().window_descriptor();
+
     App::new()
         // Window Setup
         .insert_resource(ClearColor(Color::rgb(0.3, 0.3, 0.3)))
         .add_plugins(DefaultPlugins.set(WindowPlugin {
-            window: WindowDescriptor {
-                width: WIDTH,
-                height: HEIGHT,
-                title: "Bevy Tower Defense".to_string(),
-                resizable: false,
-                ..default()
-            },
+            window,
             ..default()
         }))
         // Inspector Setup
@@ -56,16 +101,37 @@ fn main() {
         // Our State
         .add_state(GameState::MainMenu)
         // Our Systems
+        // Before anything adds systems to the simulation stage
+        .add_plugin(SimulationPlugin)
+        .add_plugin(SettingsPlugin { loaded: settings })
+        .add_plugin(InputMapPlugin)
+        .add_plugin(CameraPlugin)
+        .add_plugin(GameTimePlugin)
+        .add_plugin(ActionPlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(SavePlugin)
+        .add_plugin(LevelPlugin)
         .add_plugin(TowerPlugin)
+        .add_plugin(BeamPlugin)
+        .add_plugin(TowerAuraPlugin)
+        .add_plugin(AbilityPlugin)
+        .add_plugin(SpellPlugin)
         .add_plugin(TargetPlugin)
+        .add_plugin(WavePlugin)
+        .add_plugin(BossPlugin)
+        .add_plugin(SupportPlugin)
+        .add_plugin(StealthPlugin)
+        .add_plugin(DamagePlugin)
+        .add_plugin(PopupPlugin)
+        .add_plugin(EconomyPlugin)
+        .add_plugin(GameOverPlugin)
+        .add_plugin(TowerPanelPlugin)
         .add_plugin(BulletPlugin)
         .add_plugin(MainMenuPlugin)
         .add_plugin(PlayerPlugin)
-        //TODO despawn scene on returning to main menu (on_exit)
         .add_system_set(SystemSet::on_enter(GameState::Gameplay).with_system(spawn_basic_scene))
-        .add_startup_system(spawn_camera)
+        .add_system_set(SystemSet::on_exit(GameState::Gameplay).with_system(despawn_gameplay))
         .add_startup_system_to_stage(StartupStage::PreStartup, asset_loading)
-        .add_system(camera_controls)
         .run();
 }
 
@@ -82,44 +148,6 @@ fn asset_loading(mut commands: Commands, assets: Res<AssetServer>) {
     });
 }
 
-fn camera_controls(
-    keyboard: Res<Input<KeyCode>>,
-    mut camera_query: Query<&mut Transform, With<Camera3d>>,
-    time: Res<Time>,
-) {
-    let mut camera = camera_query.single_mut();
-
-    let mut forward = camera.forward();
-    forward.y = 0.0;
-    forward = forward.normalize();
-
-    let mut left = camera.left();
-    left.y = 0.0;
-    left = left.normalize();
-
-    let speed = 3.0;
-    let rotate_speed = 0.3;
-
-    if keyboard.pressed(KeyCode::W) {
-        camera.translation += forward * time.delta_seconds() * speed;
-    }
-    if keyboard.pressed(KeyCode::S) {
-        camera.translation -= forward * time.delta_seconds() * speed;
-    }
-    if keyboard.pressed(KeyCode::A) {
-        camera.translation += left * time.delta_seconds() * speed;
-    }
-    if keyboard.pressed(KeyCode::D) {
-        camera.translation -= left * time.delta_seconds() * speed;
-    }
-    if keyboard.pressed(KeyCode::Q) {
-        camera.rotate_axis(Vec3::Y, rotate_speed * time.delta_seconds())
-    }
-    if keyboard.pressed(KeyCode::E) {
-        camera.rotate_axis(Vec3::Y, -rotate_speed * time.delta_seconds())
-    }
-}
-
 /* Selection testing system
 fn what_is_selected(selection: Query<(&Name, &Selection)>) {
     for (name, selection) in &selection {
@@ -135,60 +163,17 @@ fn spawn_basic_scene(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     game_assets: Res<GameAssets>,
+    level: Res<Level>,
 ) {
-    commands
-        .spawn(PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Plane { size: 50.0 })),
-            material: materials.add(Color::rgb(0.3, 0.5, 0.3).into()),
-            ..default()
-        })
-        .insert(Name::new("Ground"));
-
-    let default_collider_color = materials.add(Color::rgba(0.3, 0.5, 0.3, 0.3).into());
-    let selected_collider_color = materials.add(Color::rgba(0.3, 0.9, 0.3, 0.9).into());
-
-    for i in 0..10 {
-        for j in 0..2 {
-            commands
-                .spawn(SpatialBundle::from_transform(Transform::from_xyz(
-                    2.0 * i as f32 + j as f32,
-                    0.8,
-                    5.0 * j as f32,
-                )))
-                .insert(Name::new("Tower_Base"))
-                .insert(meshes.add(shape::Capsule::default().into()))
-                .insert(Highlighting {
-                    initial: default_collider_color.clone(),
-                    hovered: Some(selected_collider_color.clone()),
-                    pressed: Some(selected_collider_color.clone()),
-                    selected: Some(selected_collider_color.clone()),
-                })
-                .insert(default_collider_color.clone())
-                .insert(NotShadowCaster)
-                .insert(PickableBundle::default())
-                .with_children(|commands| {
-                    commands.spawn(SceneBundle {
-                        scene: game_assets.tower_base_scene.clone(),
-                        transform: Transform::from_xyz(0.0, -0.8, 0.0),
-                        ..Default::default()
-                    });
-                });
-        }
-    }
-
-    for i in 1..25 {
-        commands
-            .spawn(SceneBundle {
-                scene: game_assets.target_scene.clone(),
-                transform: Transform::from_xyz(-2.0 * i as f32, 0.4, 2.5),
-                ..Default::default()
-            })
-            .insert(Target {
-                speed: 0.45,
-                ..Default::default()
-            })
-            .insert(Health { value: 3 })
-            .insert(Name::new("Target"));
+    //Towers reuse these when they replace a base so they can be selected too
+    let colliders = SelectionColliders {
+        mesh: meshes.add(shape::Capsule::default().into()),
+        default_color: materials.add(Color::rgba(0.3, 0.5, 0.3, 0.3).into()),
+        selected_color: materials.add(Color::rgba(0.3, 0.9, 0.3, 0.9).into()),
+    };
+
+    for site in &level.tower_sites {
+        spawn_tower_base(&mut commands, &game_assets, &colliders, *site);
     }
 
     commands
@@ -202,13 +187,16 @@ fn spawn_basic_scene(
             ..default()
         })
         .insert(Name::new("Light"));
+
+    commands.insert_resource(colliders);
 }
 
-fn spawn_camera(mut commands: Commands) {
-    commands
-        .spawn(Camera3dBundle {
-            transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
-            ..default()
-        })
-        .insert(PickingCameraBundle::default());
+//Everything but the camera is spawned when gameplay starts, this clears the way for a restart
+fn despawn_gameplay(
+    mut commands: Commands,
+    entities: Query<Entity, (Without<Parent>, Without<Camera>)>,
+) {
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
 }
@@ -1,6 +1,10 @@
 use bevy::{app::AppExit, prelude::*};
 
-use crate::GameState;
+use crate::{
+    autosave_path, load_save, AirPath, GameState, LastSession, Level, NoticeEvent,
+    OpenSettingsButton, Replay, ReplayMode, ReplayState, SaveGames, TargetPath, REPLAY_PATH,
+    SAVE_PATH,
+};
 
 #[derive(Component)]
 pub struct MenuUIRoot;
@@ -11,6 +15,16 @@ pub struct StartButton;
 #[derive(Component)]
 pub struct QuitButton;
 
+#[derive(Component)]
+pub struct WatchReplayButton;
+
+#[derive(Component)]
+pub enum LoadGameButton {
+    Quicksave,
+    /// Newest autosave, only offered when the last game didn't end properly
+    Continue,
+}
+
 pub struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
@@ -20,20 +34,115 @@ impl Plugin for MainMenuPlugin {
             .add_system_set(
                 SystemSet::on_update(GameState::MainMenu)
                     .with_system(start_button_clicked)
+                    .with_system(replay_button_clicked)
+                    .with_system(load_button_clicked)
                     .with_system(quit_button_clicked),
             );
     }
 }
 
+/// Puts back the normal level after a replay brought its own
+pub fn use_default_level(level: &mut Level, path: &mut TargetPath, air_path: &mut AirPath) {
+    *level = Level::default();
+    (*path, *air_path) = level.paths();
+}
+
+#[allow(clippy::too_many_arguments)]
 fn start_button_clicked(
     mut commands: Commands,
     interactions: Query<&Interaction, (With<StartButton>, Changed<Interaction>)>,
     menu_root: Query<Entity, With<MenuUIRoot>>,
     mut game_state: ResMut<State<GameState>>,
     mut mouse_input: ResMut<Input<MouseButton>>,
+    mut replay_state: ResMut<ReplayState>,
+    mut level: ResMut<Level>,
+    mut path: ResMut<TargetPath>,
+    mut air_path: ResMut<AirPath>,
 ) {
     for interaction in &interactions {
         if matches!(interaction, Interaction::Clicked) {
+            replay_state.mode = ReplayMode::Recording;
+            use_default_level(&mut level, &mut path, &mut air_path);
+            let root_entity = menu_root.single();
+            commands.entity(root_entity).despawn_recursive();
+
+            game_state.set(GameState::Gameplay).unwrap();
+            mouse_input.clear();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn replay_button_clicked(
+    mut commands: Commands,
+    interactions: Query<&Interaction, (With<WatchReplayButton>, Changed<Interaction>)>,
+    menu_root: Query<Entity, With<MenuUIRoot>>,
+    mut game_state: ResMut<State<GameState>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+    mut replay_state: ResMut<ReplayState>,
+    mut level: ResMut<Level>,
+    mut path: ResMut<TargetPath>,
+    mut air_path: ResMut<AirPath>,
+    mut notices: EventWriter<NoticeEvent>,
+) {
+    for interaction in &interactions {
+        if matches!(interaction, Interaction::Clicked) {
+            let replay = match Replay::load(REPLAY_PATH) {
+                Ok(replay) => replay,
+                Err(err) => {
+                    error!("Failed to load replay: {}", err);
+                    notices.send(NoticeEvent::error(format!("Can't load replay: {}", err)));
+                    continue;
+                }
+            };
+            //The replay is played on the level it was recorded on
+            *level = replay.level.clone();
+            (*path, *air_path) = level.paths();
+            replay_state.play(replay);
+
+            let root_entity = menu_root.single();
+            commands.entity(root_entity).despawn_recursive();
+
+            game_state.set(GameState::Gameplay).unwrap();
+            mouse_input.clear();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn load_button_clicked(
+    mut commands: Commands,
+    interactions: Query<(&Interaction, &LoadGameButton), Changed<Interaction>>,
+    menu_root: Query<Entity, With<MenuUIRoot>>,
+    mut game_state: ResMut<State<GameState>>,
+    mut mouse_input: ResMut<Input<MouseButton>>,
+    mut replay_state: ResMut<ReplayState>,
+    mut saves: ResMut<SaveGames>,
+    registry: Res<AppTypeRegistry>,
+    mut level: ResMut<Level>,
+    mut path: ResMut<TargetPath>,
+    mut air_path: ResMut<AirPath>,
+    mut notices: EventWriter<NoticeEvent>,
+) {
+    for (interaction, button) in &interactions {
+        if matches!(interaction, Interaction::Clicked) {
+            let save_path = match button {
+                LoadGameButton::Quicksave => SAVE_PATH.into(),
+                LoadGameButton::Continue => autosave_path(0),
+            };
+            let scene = match load_save(save_path, &registry) {
+                Ok(scene) => scene,
+                Err(err) => {
+                    error!("Failed to load game: {}", err);
+                    notices.send(NoticeEvent::error(format!("Can't load game: {}", err)));
+                    continue;
+                }
+            };
+            //Restored once gameplay has set up the level
+            saves.pending = Some(scene);
+            replay_state.mode = ReplayMode::Recording;
+            use_default_level(&mut level, &mut path, &mut air_path);
+
             let root_entity = menu_root.single();
             commands.entity(root_entity).despawn_recursive();
 
@@ -54,43 +163,76 @@ fn quit_button_clicked(
     }
 }
 
-fn spawn_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn spawn_main_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    last_session: Res<LastSession>,
+) {
+    let continue_button = (last_session.crashed && autosave_path(0).exists()).then(|| {
+        let button = spawn_button(
+            &mut commands,
+            &asset_server,
+            "Continue last game",
+            Color::GOLD,
+        );
+        commands
+            .entity(button)
+            .insert(LoadGameButton::Continue)
+            .id()
+    });
+
     let start_button = spawn_button(&mut commands, &asset_server, "Start Game", Color::RED);
     commands.entity(start_button).insert(StartButton);
 
+    let load_button = spawn_button(&mut commands, &asset_server, "Load Game", Color::ORANGE);
+    commands
+        .entity(load_button)
+        .insert(LoadGameButton::Quicksave);
+
+    let replay_button = spawn_button(&mut commands, &asset_server, "Watch Replay", Color::GREEN);
+    commands.entity(replay_button).insert(WatchReplayButton);
+
+    let settings_button = spawn_button(&mut commands, &asset_server, "Settings", Color::GRAY);
+    commands.entity(settings_button).insert(OpenSettingsButton);
+
     let quit_button = spawn_button(&mut commands, &asset_server, "Quit", Color::BLUE);
     commands.entity(quit_button).insert(QuitButton);
 
-    commands
-        .spawn(NodeBundle {
+    let mut root = commands.spawn(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        ..default()
+    });
+    root.insert(MenuUIRoot).with_children(|commands| {
+        commands.spawn(TextBundle {
             style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                justify_content: JustifyContent::Center,
-                flex_direction: FlexDirection::Column,
+                align_self: AlignSelf::Center,
+                margin: UiRect::all(Val::Percent(3.0)),
                 ..default()
             },
-            ..default()
-        })
-        .insert(MenuUIRoot)
-        .with_children(|commands| {
-            commands.spawn(TextBundle {
-                style: Style {
-                    align_self: AlignSelf::Center,
-                    margin: UiRect::all(Val::Percent(3.0)),
-                    ..default()
+            text: Text::from_section(
+                "Tower Defense Tutorial",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 96.0,
+                    color: Color::BLACK,
                 },
-                text: Text::from_section(
-                    "Tower Defense Tutorial",
-                    TextStyle {
-                        font: asset_server.load("FiraSans-Bold.ttf"),
-                        font_size: 96.0,
-                        color: Color::BLACK,
-                    },
-                ),
-                ..default()
-            });
-        })
-        .add_child(start_button)
+            ),
+            ..default()
+        });
+    });
+    //Picking up where the crash left off comes first
+    if let Some(continue_button) = continue_button {
+        root.add_child(continue_button);
+    }
+    root.add_child(start_button)
+        .add_child(load_button)
+        .add_child(replay_button)
+        .add_child(settings_button)
         .add_child(quit_button);
 }
 
@@ -103,10 +245,10 @@ fn spawn_button(
     commands
         .spawn(ButtonBundle {
             style: Style {
-                size: Size::new(Val::Percent(65.0), Val::Percent(15.0)),
+                size: Size::new(Val::Percent(65.0), Val::Percent(10.0)),
                 align_self: AlignSelf::Center,
                 justify_content: JustifyContent::Center,
-                margin: UiRect::all(Val::Percent(2.0)),
+                margin: UiRect::all(Val::Percent(1.0)),
                 ..default()
             },
             background_color: color.into(),
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::{GameState, TargetDeathEvent};
+use crate::{GameState, GameStats, SimulationStage, SimulationStep, TargetDeathEvent, WaveUI};
 
 // Could be a resource
 #[derive(Component, Reflect, Default)]
@@ -29,10 +29,12 @@ impl Plugin for PlayerPlugin {
                     .with_system(spawn_player)
                     .with_system(spawn_gameplay_ui),
             )
-            .add_system_set(
-                SystemSet::on_update(GameState::Gameplay)
-                    .with_system(give_money_on_kill)
-                    .with_system(update_player_ui),
+            .add_system_set(SystemSet::on_update(GameState::Gameplay).with_system(update_player_ui))
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Economy
+                    .set()
+                    .with_system(give_money_on_kill),
             );
     }
 }
@@ -102,6 +104,23 @@ fn spawn_gameplay_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                             ..default()
                         })
                         .insert(MoneyUI);
+                    commands
+                        .spawn(TextBundle {
+                            style: Style {
+                                margin: UiRect::all(Val::Percent(1.2)),
+                                ..default()
+                            },
+                            text: Text::from_section(
+                                "Wave: XX",
+                                TextStyle {
+                                    font: asset_server.load("FiraSans-Bold.ttf"),
+                                    font_size: 36.0,
+                                    color: Color::BLACK,
+                                },
+                            ),
+                            ..default()
+                        })
+                        .insert(WaveUI);
                     commands
                         .spawn(TextBundle {
                             style: Style {
@@ -135,10 +154,13 @@ fn spawn_player(mut commands: Commands) {
 
 fn give_money_on_kill(
     mut player: Query<&mut Player>,
+    mut stats: ResMut<GameStats>,
     mut death_events: EventReader<TargetDeathEvent>,
 ) {
     let mut player = player.single_mut();
     for _event in death_events.iter() {
         player.money += 10;
+        stats.kills += 1;
+        stats.kill_income += 10;
     }
 }
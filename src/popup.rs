@@ -0,0 +1,171 @@
+use crate::*;
+
+/// Text that floats up from a point in the world and fades out
+#[derive(Component)]
+pub struct Popup {
+    pub world_position: Vec3,
+    pub timer: Timer,
+}
+
+/// Message shown at the top of the screen for a few seconds, in the menu as well as in game
+pub struct NoticeEvent {
+    pub text: String,
+    pub color: Color,
+}
+
+impl NoticeEvent {
+    pub fn info(text: impl Into<String>) -> Self {
+        NoticeEvent {
+            text: text.into(),
+            color: Color::WHITE,
+        }
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        NoticeEvent {
+            text: text.into(),
+            color: Color::RED,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Notice {
+    pub timer: Timer,
+}
+
+pub struct PopupPlugin;
+
+impl Plugin for PopupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<NoticeEvent>()
+            .add_system(spawn_notices)
+            .add_system(update_notices)
+            .add_system_set(SystemSet::on_update(GameState::Gameplay).with_system(update_popups));
+    }
+}
+
+pub fn spawn_popup(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    text: &str,
+    color: Color,
+    world_position: Vec3,
+) {
+    commands
+        .spawn(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            text: Text::from_section(
+                text,
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 24.0,
+                    color,
+                },
+            ),
+            // Hidden until it has been placed over its world position
+            visibility: Visibility::INVISIBLE,
+            ..default()
+        })
+        .insert(Popup {
+            world_position,
+            timer: Timer::from_seconds(1.0, TimerMode::Once),
+        })
+        .insert(Name::new("Popup"));
+}
+
+fn update_popups(
+    mut commands: Commands,
+    mut popups: Query<(Entity, &mut Popup, &mut Style, &mut Text, &mut Visibility)>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    time: Res<Time>,
+) {
+    let (camera, camera_transform) = camera.single();
+    for (entity, mut popup, mut style, mut text, mut visibility) in &mut popups {
+        popup.timer.tick(time.delta());
+        if popup.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        popup.world_position.y += 0.6 * time.delta_seconds();
+        match camera.world_to_viewport(camera_transform, popup.world_position) {
+            Some(screen_position) => {
+                visibility.is_visible = true;
+                style.position = UiRect {
+                    left: Val::Px(screen_position.x),
+                    bottom: Val::Px(screen_position.y),
+                    ..default()
+                };
+            }
+            None => visibility.is_visible = false,
+        }
+        text.sections[0]
+            .style
+            .color
+            .set_a(popup.timer.percent_left());
+    }
+}
+
+fn spawn_notices(
+    mut commands: Commands,
+    mut events: EventReader<NoticeEvent>,
+    old_notices: Query<Entity, With<Notice>>,
+    asset_server: Res<AssetServer>,
+) {
+    //Only the newest one is shown
+    let Some(notice) = events.iter().last() else {
+        return;
+    };
+    for entity in &old_notices {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands
+        .spawn(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(30.0),
+                    top: Val::Percent(2.0),
+                    ..default()
+                },
+                max_size: Size::new(Val::Percent(40.0), Val::Undefined),
+                ..default()
+            },
+            text: Text::from_section(
+                notice.text.clone(),
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 24.0,
+                    color: notice.color,
+                },
+            ),
+            ..default()
+        })
+        .insert(Notice {
+            timer: Timer::from_seconds(4.0, TimerMode::Once),
+        })
+        .insert(Name::new("Notice"));
+}
+
+fn update_notices(
+    mut commands: Commands,
+    mut notices: Query<(Entity, &mut Notice, &mut Text)>,
+    time: Res<Time>,
+) {
+    for (entity, mut notice, mut text) in &mut notices {
+        notice.timer.tick(time.delta());
+        if notice.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        //Fades over the last second
+        text.sections[0]
+            .style
+            .color
+            .set_a(notice.timer.remaining_secs().min(1.0));
+    }
+}
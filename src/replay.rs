@@ -0,0 +1,417 @@
+use std::{fs, path::Path};
+
+use ron::ser::PrettyConfig;
+
+use crate::*;
+
+/// Bumped whenever `Replay` or `PlayerAction` changes, with a step added to `REPLAY_MIGRATIONS`
+pub const REPLAY_VERSION: u32 = 2;
+pub const REPLAY_PATH: &str = "replays/last_replay.ron";
+
+/// Every step from the first replay format to the current one, see `upgrade`
+const REPLAY_MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    migrate: add_wave_checks,
+}];
+
+//Version 1 replays have nothing to check playback against
+fn add_wave_checks(text: String) -> Result<String, String> {
+    let start = text.find('(').ok_or("replay is not a struct")?;
+    Ok(format!(
+        "{}wave_checks: [], {}",
+        &text[..=start],
+        &text[start + 1..]
+    ))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedAction {
+    pub tick: u64,
+    pub action: PlayerAction,
+}
+
+/// A few numbers taken at each wave start, cheap to compare and quick to go wrong
+/// if playback simulates a different game than the one recorded
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StateCheck {
+    pub tick: u64,
+    pub money: u32,
+    pub health: u32,
+    pub targets: usize,
+}
+
+/// Everything needed to simulate a match again, the level carries the simulation seed
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Replay {
+    pub version: u32,
+    pub level: Level,
+    pub actions: Vec<RecordedAction>,
+    /// Tick each wave started on, for jumping straight to a wave
+    pub wave_starts: Vec<u64>,
+    /// Taken on the same ticks as `wave_starts`
+    pub wave_checks: Vec<StateCheck>,
+}
+
+impl Replay {
+    pub fn new(level: Level) -> Self {
+        Replay {
+            version: REPLAY_VERSION,
+            level,
+            actions: Vec::new(),
+            wave_starts: Vec::new(),
+            wave_checks: Vec::new(),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let text = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| err.to_string())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let text = upgrade(text, REPLAY_VERSION, REPLAY_MIGRATIONS)?;
+        ron::from_str(&text).map_err(|err| err.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// A normal game, the player's actions are written down as they happen
+    #[default]
+    Recording,
+    /// Actions come from the replay and the player only watches
+    Playback,
+}
+
+#[derive(Resource)]
+pub struct ReplayState {
+    pub mode: ReplayMode,
+    pub replay: Replay,
+    next_action: usize,
+    /// Off for games continued from a save, a replay starts from an empty level so the
+    /// actions taken after loading couldn't be simulated again
+    recording: bool,
+    /// Only the first difference is reported, everything after it differs anyway
+    diverged: bool,
+}
+
+impl Default for ReplayState {
+    fn default() -> Self {
+        ReplayState {
+            mode: ReplayMode::Recording,
+            replay: Replay::new(Level::default()),
+            next_action: 0,
+            recording: true,
+            diverged: false,
+        }
+    }
+}
+
+impl ReplayState {
+    /// Switches to watching a replay, the level has to be put in place before gameplay starts
+    pub fn play(&mut self, replay: Replay) {
+        self.mode = ReplayMode::Playback;
+        self.replay = replay;
+        self.next_action = 0;
+        self.diverged = false;
+    }
+}
+
+#[derive(Component)]
+pub struct ReplayBar;
+
+#[derive(Component)]
+pub enum ReplayButton {
+    /// Jumps to the start of this wave, counting from 0
+    Wave(usize),
+    Exit,
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayState>()
+            .add_system_set(SystemSet::on_enter(GameState::Gameplay).with_system(start_replay))
+            .add_system_set(
+                SystemSet::on_update(GameState::Gameplay).with_system(replay_buttons_clicked),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Input
+                    .set()
+                    .with_system(dispatch_player_actions),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Economy.set().with_system(record_waves),
+            );
+    }
+}
+
+fn start_replay(
+    mut commands: Commands,
+    mut replay_state: ResMut<ReplayState>,
+    saves: Res<SaveGames>,
+    level: Res<Level>,
+    asset_server: Res<AssetServer>,
+) {
+    match replay_state.mode {
+        ReplayMode::Recording => {
+            replay_state.replay = Replay::new(level.clone());
+            //The save is restored later this frame
+            replay_state.recording = saves.pending.is_none();
+            if !replay_state.recording {
+                info!("Not recording a replay of a game loaded from a save");
+            }
+        }
+        ReplayMode::Playback => {
+            replay_state.next_action = 0;
+            replay_state.diverged = false;
+            spawn_replay_bar(&mut commands, &asset_server, &replay_state.replay);
+        }
+    }
+}
+
+/// The one place player actions enter the simulation, so recording and playback can't miss any
+fn dispatch_player_actions(
+    mut requests: EventReader<PlayerActionRequest>,
+    mut actions: EventWriter<PlayerActionEvent>,
+    mut replay_state: ResMut<ReplayState>,
+    game_time: Res<GameTime>,
+) {
+    match replay_state.mode {
+        ReplayMode::Recording => {
+            for PlayerActionRequest(action) in requests.iter() {
+                if replay_state.recording {
+                    replay_state.replay.actions.push(RecordedAction {
+                        tick: game_time.tick,
+                        action: action.clone(),
+                    });
+                }
+                actions.send(PlayerActionEvent(action.clone()));
+            }
+        }
+        ReplayMode::Playback => {
+            //The player is only watching
+            requests.clear();
+            while let Some(recorded) = replay_state.replay.actions.get(replay_state.next_action) {
+                if recorded.tick > game_time.tick {
+                    break;
+                }
+                actions.send(PlayerActionEvent(recorded.action.clone()));
+                replay_state.next_action += 1;
+            }
+        }
+    }
+}
+
+// Written out at wave boundaries, rarely enough to be cheap and often enough that the file
+// is still usable if the game is closed mid match
+#[allow(clippy::too_many_arguments)]
+fn record_waves(
+    mut start_events: EventReader<WaveStartEvent>,
+    mut cleared_events: EventReader<WaveClearedEvent>,
+    mut replay_state: ResMut<ReplayState>,
+    player: Query<&Player>,
+    targets: Query<(), With<Target>>,
+    game_time: Res<GameTime>,
+    mut notices: EventWriter<NoticeEvent>,
+) {
+    let started = start_events.iter().count() > 0;
+    let cleared = cleared_events.iter().count() > 0;
+    let check = started.then(|| {
+        let player = player.single();
+        StateCheck {
+            tick: game_time.tick,
+            money: player.money,
+            health: player.health,
+            targets: targets.iter().count(),
+        }
+    });
+
+    if replay_state.mode == ReplayMode::Playback {
+        if let Some(check) = check {
+            verify_playback(&mut replay_state, check, &mut notices);
+        }
+        return;
+    }
+    if !replay_state.recording || !(started || cleared) {
+        return;
+    }
+    if let Some(check) = check {
+        replay_state.replay.wave_starts.push(game_time.tick);
+        replay_state.replay.wave_checks.push(check);
+    }
+    if let Err(err) = replay_state.replay.save(REPLAY_PATH) {
+        error!("Failed to save replay: {}", err);
+        notices.send(NoticeEvent::error(format!("Can't save replay: {}", err)));
+    }
+}
+
+fn verify_playback(
+    replay_state: &mut ReplayState,
+    check: StateCheck,
+    notices: &mut EventWriter<NoticeEvent>,
+) {
+    //Replays from before checks were recorded can't be verified
+    let Some(last) = replay_state.replay.wave_checks.last() else {
+        return;
+    };
+    //The recording stopped there, a game quit partway keeps playing past its last check
+    if replay_state.diverged || check.tick > last.tick {
+        return;
+    }
+    let expected = replay_state
+        .replay
+        .wave_checks
+        .iter()
+        .find(|recorded| recorded.tick == check.tick)
+        .cloned();
+    if expected.as_ref() == Some(&check) {
+        return;
+    }
+    replay_state.diverged = true;
+    warn!(
+        "Replay diverged at tick {}, expected {:?} but got {:?}",
+        check.tick, expected, check
+    );
+    notices.send(NoticeEvent::error(format!(
+        "Replay no longer matches the recorded game (tick {})",
+        check.tick
+    )));
+}
+
+fn spawn_replay_bar(commands: &mut Commands, asset_server: &AssetServer, replay: &Replay) {
+    let text_style = TextStyle {
+        font: asset_server.load("FiraSans-Bold.ttf"),
+        font_size: 20.0,
+        color: Color::BLACK,
+    };
+    let button_style = Style {
+        size: Size::new(Val::Px(72.0), Val::Px(30.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        margin: UiRect::left(Val::Px(4.0)),
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(1.0),
+                    top: Val::Percent(19.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(ReplayBar)
+        .insert(Name::new("ReplayBar"))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section("REPLAY", text_style.clone()));
+            for wave in 0..replay.wave_starts.len() {
+                commands
+                    .spawn(ButtonBundle {
+                        style: button_style.clone(),
+                        ..default()
+                    })
+                    .insert(ReplayButton::Wave(wave))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            format!("Wave {}", wave + 1),
+                            text_style.clone(),
+                        ));
+                    });
+            }
+            commands
+                .spawn(ButtonBundle {
+                    style: button_style,
+                    background_color: Color::ORANGE.into(),
+                    ..default()
+                })
+                .insert(ReplayButton::Exit)
+                .with_children(|commands| {
+                    commands.spawn(TextBundle::from_section("Exit", text_style));
+                });
+        });
+}
+
+fn replay_buttons_clicked(
+    interactions: Query<(&Interaction, &ReplayButton), Changed<Interaction>>,
+    mut replay_state: ResMut<ReplayState>,
+    mut game_time: ResMut<GameTime>,
+    mut game_state: ResMut<State<GameState>>,
+    mut notices: EventWriter<NoticeEvent>,
+) {
+    for (interaction, button) in &interactions {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+        match button {
+            ReplayButton::Wave(wave) => {
+                let Some(tick) = replay_state.replay.wave_starts.get(*wave).copied() else {
+                    continue;
+                };
+                game_time.seek_to = Some(tick);
+                //The simulation only runs forwards, going back means starting over
+                if tick <= game_time.tick {
+                    if let Err(err) = game_state.restart() {
+                        error!("Failed to restart replay: {:?}", err);
+                        notices.send(NoticeEvent::error("Can't jump back in the replay"));
+                        game_time.seek_to = None;
+                    }
+                }
+            }
+            ReplayButton::Exit => match game_state.set(GameState::MainMenu) {
+                Ok(()) => replay_state.mode = ReplayMode::Recording,
+                Err(err) => {
+                    error!("Failed to leave replay: {:?}", err);
+                    notices.send(NoticeEvent::error("Can't leave the replay"));
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_version_1_replay() {
+        let replay = Replay::load(fixture("replay_v1.ron")).unwrap();
+        //Levels had no names yet, the only one there was is the default
+        assert_eq!(replay.level.name, "Meadow");
+        assert_eq!(replay.actions.len(), 2);
+        assert_eq!(replay.wave_starts, vec![301]);
+        //Recorded before the checks, nothing to verify against
+        assert!(replay.wave_checks.is_empty());
+    }
+
+    #[test]
+    fn loads_version_2_replay() {
+        let replay = Replay::load(fixture("replay_v2.ron")).unwrap();
+        assert_eq!(replay.actions.len(), 2);
+        assert_eq!(
+            replay.wave_checks,
+            vec![StateCheck {
+                tick: 301,
+                money: 50,
+                health: 10,
+                targets: 0,
+            }]
+        );
+    }
+}
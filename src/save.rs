@@ -0,0 +1,643 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    app::AppExit,
+    reflect::{TypeRegistryArc, TypeRegistryInternal},
+    scene::{
+        serde::{SceneDeserializer, SceneSerializer},
+        DynamicEntity,
+    },
+};
+use ron::ser::PrettyConfig;
+use serde::{
+    de::{self, DeserializeSeed, IgnoredAny, MapAccess, Visitor},
+    Deserializer,
+};
+
+use crate::*;
+
+/// Bumped whenever a saved component changes, with a step added to `SAVE_MIGRATIONS`
+pub const SAVE_VERSION: u32 = 2;
+pub const SAVE_PATH: &str = "saves/quicksave.ron";
+
+/// Autosaves kept around, `autosave_0` is always the newest
+const AUTOSAVE_COUNT: usize = 3;
+
+/// Exists while a game is being played, finding it on launch means the last game crashed
+const SESSION_MARKER_PATH: &str = "saves/session.lock";
+
+/// Everything in a save that doesn't live on an entity, stored on an entity of its own.
+/// Bullets in flight and beam locks aren't saved, towers pick their targets again.
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct SavedGame {
+    pub tick: u64,
+    pub rng: u64,
+    pub wave: usize,
+    pub wave_in_progress: bool,
+    pub remaining: Vec<EnemyType>,
+    pub spawn_timer: Timer,
+    pub break_timer: Timer,
+    pub spell_cooldowns: Vec<SpellCooldown>,
+    pub stats: GameStats,
+}
+
+#[derive(Reflect, FromReflect, Clone)]
+pub struct SpellCooldown {
+    pub spell: SpellType,
+    pub timer: Timer,
+}
+
+#[derive(Resource, Default)]
+pub struct SaveGames {
+    /// Set by the UI, the game is written out after this frame's ticks
+    pub save_requested: bool,
+    /// Set when a wave starts, written out with the quicksave
+    pub autosave_requested: bool,
+    /// Save waiting for gameplay to start, restored before the first tick
+    pub pending: Option<DynamicScene>,
+}
+
+/// Whether the game before this one ended without going back to the menu or quitting
+#[derive(Resource, Default)]
+pub struct LastSession {
+    pub crashed: bool,
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        //Bevy doesn't register everything a saved `Timer` is made of
+        app.register_type::<TimerMode>()
+            .register_type::<SavedGame>()
+            .register_type::<SpellCooldown>()
+            .register_type::<Vec<SpellCooldown>>()
+            .register_type::<Vec<EnemyType>>()
+            .init_resource::<SaveGames>()
+            .insert_resource(LastSession {
+                crashed: Path::new(SESSION_MARKER_PATH).exists(),
+            })
+            .add_system_set(SystemSet::on_enter(GameState::Gameplay).with_system(start_session))
+            .add_system_set(SystemSet::on_exit(GameState::Gameplay).with_system(end_session))
+            .add_system_set(
+                SystemSet::on_update(GameState::Gameplay).with_system(save_load_hotkeys),
+            )
+            .add_system_to_stage(CoreStage::Last, end_session_on_exit)
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Economy.set().with_system(request_autosave),
+            )
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                SystemSet::on_update(GameState::Gameplay)
+                    .with_system(save_game)
+                    .with_system(restore_game),
+            );
+    }
+}
+
+/// Reads a save, upgrading it from older versions, the registry needs every saved type registered
+pub fn load_save(
+    path: impl AsRef<Path>,
+    registry: &TypeRegistryArc,
+) -> Result<DynamicScene, String> {
+    let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let text = upgrade(text, SAVE_VERSION, SAVE_MIGRATIONS)?;
+    let mut deserializer = ron::de::Deserializer::from_str(&text).map_err(|err| err.to_string())?;
+    let scene = SaveFileDeserializer {
+        type_registry: &registry.read(),
+    }
+    .deserialize(&mut deserializer)
+    .map_err(|err| err.to_string())?;
+
+    match saved_game(&scene) {
+        Some(_) => Ok(scene),
+        None => Err("save has no game state".to_string()),
+    }
+}
+
+fn write_save(
+    scene: &DynamicScene,
+    registry: &TypeRegistryArc,
+    path: impl AsRef<Path>,
+) -> Result<(), String> {
+    let path = path.as_ref();
+    //A crash halfway through writing leaves the old file alone
+    let temp_path = write_temp_save(scene, registry, path)?;
+    fs::rename(&temp_path, path).map_err(|err| err.to_string())
+}
+
+/// Writes the save next to `path` without touching `path` itself, returns where it went
+fn write_temp_save(
+    scene: &DynamicScene,
+    registry: &TypeRegistryArc,
+    path: &Path,
+) -> Result<PathBuf, String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    let file = SaveFile {
+        version: SAVE_VERSION,
+        scene: SceneSerializer::new(scene, registry),
+    };
+    let text = ron::ser::to_string_pretty(&file, PrettyConfig::default())
+        .map_err(|err| err.to_string())?;
+    let temp_path = path.with_extension("ron.tmp");
+    fs::write(&temp_path, text).map_err(|err| err.to_string())?;
+    Ok(temp_path)
+}
+
+pub fn autosave_path(index: usize) -> PathBuf {
+    PathBuf::from(format!("saves/autosave_{}.ron", index))
+}
+
+/// Moves every autosave one slot older to make room at `autosave_0`, the oldest one drops off
+fn rotate_autosaves() -> Result<(), String> {
+    for index in (0..AUTOSAVE_COUNT - 1).rev() {
+        let path = autosave_path(index);
+        if path.exists() {
+            fs::rename(&path, autosave_path(index + 1)).map_err(|err| err.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Only rotates once the new save is safely written, a failed write keeps every old autosave
+fn write_autosave(scene: &DynamicScene, registry: &TypeRegistryArc) -> Result<(), String> {
+    let path = autosave_path(0);
+    let temp_path = write_temp_save(scene, registry, &path)?;
+    rotate_autosaves()?;
+    fs::rename(&temp_path, path).map_err(|err| err.to_string())
+}
+
+/// The version sits outside the scene so it can be read before the scene is
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    version: u32,
+    scene: SceneSerializer<'a>,
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum SaveFileField {
+    Version,
+    Scene,
+}
+
+/// Reads the scene out of a `SaveFile`, the version has already been checked by then
+struct SaveFileDeserializer<'a> {
+    type_registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for SaveFileDeserializer<'a> {
+    type Value = DynamicScene;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("SaveFile", &["version", "scene"], self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for SaveFileDeserializer<'a> {
+    type Value = DynamicScene;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a save file")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut scene = None;
+        while let Some(field) = map.next_key::<SaveFileField>()? {
+            match field {
+                SaveFileField::Scene => {
+                    scene = Some(map.next_value_seed(SceneDeserializer {
+                        type_registry: self.type_registry,
+                    })?);
+                }
+                SaveFileField::Version => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        scene.ok_or_else(|| de::Error::missing_field("scene"))
+    }
+}
+
+/// Every step from the first save format to the current one, see `upgrade`
+const SAVE_MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    migrate: wrap_bare_scene,
+}];
+
+/// Version 1 saves were a bare scene with the version kept on `SavedGame`
+fn wrap_bare_scene(text: String) -> Result<String, String> {
+    let saved_game = text
+        .find("\"bevy_tower_defense::save::SavedGame\"")
+        .ok_or("save has no game state")?;
+    let version_start = text[saved_game..]
+        .find("version:")
+        .map(|index| saved_game + index)
+        .ok_or("game state has no version")?;
+    let version_end = text[version_start..]
+        .find(',')
+        .map(|index| version_start + index + 1)
+        .ok_or("game state has no version")?;
+    Ok(format!(
+        "(\n  version: 2,\n  scene: {}{}\n)",
+        &text[..version_start],
+        text[version_end..].trim_end()
+    ))
+}
+
+fn find_component<T: Reflect>(entity: &DynamicEntity) -> Option<&dyn Reflect> {
+    entity
+        .components
+        .iter()
+        .find(|component| component.type_name() == std::any::type_name::<T>())
+        .map(|component| component.as_ref())
+}
+
+fn saved_game(scene: &DynamicScene) -> Option<SavedGame> {
+    scene.entities.iter().find_map(|entity| {
+        let component = find_component::<SavedGame>(entity)?;
+        let mut saved = SavedGame::default();
+        saved.apply(component);
+        Some(saved)
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_load_hotkeys(
+    input: ActionInput,
+    mut saves: ResMut<SaveGames>,
+    mut replay_state: ResMut<ReplayState>,
+    registry: Res<AppTypeRegistry>,
+    mut game_state: ResMut<State<GameState>>,
+    mut level: ResMut<Level>,
+    mut path: ResMut<TargetPath>,
+    mut air_path: ResMut<AirPath>,
+    mut notices: EventWriter<NoticeEvent>,
+) {
+    //Watching a replay isn't a game of your own to save
+    if input.just_pressed(InputAction::QuickSave) && replay_state.mode == ReplayMode::Recording {
+        saves.save_requested = true;
+    }
+    if input.just_pressed(InputAction::QuickLoad) {
+        match load_save(SAVE_PATH, &registry) {
+            Ok(scene) => {
+                match game_state.restart() {
+                    Ok(()) => {
+                        saves.pending = Some(scene);
+                        //Loading from a replay leaves its level behind
+                        replay_state.mode = ReplayMode::Recording;
+                        use_default_level(&mut level, &mut path, &mut air_path);
+                    }
+                    Err(err) => {
+                        error!("Failed to restart for loading: {:?}", err);
+                        notices.send(NoticeEvent::error("Can't load game right now"));
+                        //Would otherwise be restored into whatever game starts next
+                        saves.pending = None;
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Failed to load game: {}", err);
+                notices.send(NoticeEvent::error(format!("Can't load game: {}", err)));
+            }
+        }
+    }
+}
+
+fn request_autosave(
+    mut start_events: EventReader<WaveStartEvent>,
+    mut saves: ResMut<SaveGames>,
+    replay_state: Res<ReplayState>,
+) {
+    if start_events.iter().count() > 0 && replay_state.mode == ReplayMode::Recording {
+        saves.autosave_requested = true;
+    }
+}
+
+fn start_session(mut last_session: ResMut<LastSession>) {
+    //Playing on means the crashed game isn't worth offering any more
+    last_session.crashed = false;
+    if let Err(err) = fs::create_dir_all("saves").and_then(|_| fs::write(SESSION_MARKER_PATH, "")) {
+        warn!("Can't write session marker: {}", err);
+    }
+}
+
+fn end_session() {
+    let _ = fs::remove_file(SESSION_MARKER_PATH);
+}
+
+fn end_session_on_exit(mut exit_events: EventReader<AppExit>) {
+    if exit_events.iter().count() > 0 {
+        end_session();
+    }
+}
+
+/// Clones the listed components of an entity that has them into a reflected entity
+macro_rules! capture {
+    ($world:expr, $entity:expr, $($component:ty),+) => {{
+        let mut components: Vec<Box<dyn Reflect>> = Vec::new();
+        $(
+            if let Some(component) = $world.get::<$component>($entity) {
+                components.push(component.clone_value());
+            }
+        )+
+        DynamicEntity {
+            entity: $entity.index(),
+            components,
+        }
+    }};
+}
+
+fn save_game(world: &mut World) {
+    let mut saves = world.resource_mut::<SaveGames>();
+    let quicksave = std::mem::take(&mut saves.save_requested);
+    let autosave = std::mem::take(&mut saves.autosave_requested);
+    if !quicksave && !autosave {
+        return;
+    }
+
+    let wave_state = world.resource::<WaveState>();
+    let stats = world.resource::<GameStats>();
+    let saved = SavedGame {
+        tick: world.resource::<GameTime>().tick,
+        rng: world.resource::<SimulationRng>().state(),
+        wave: wave_state.wave,
+        wave_in_progress: wave_state.in_progress,
+        remaining: wave_state.remaining.iter().copied().collect(),
+        spawn_timer: wave_state.spawn_timer.clone(),
+        break_timer: wave_state.break_timer.clone(),
+        spell_cooldowns: world
+            .resource::<Spellbook>()
+            .cooldowns
+            .iter()
+            .map(|(spell, timer)| SpellCooldown {
+                spell: *spell,
+                timer: timer.clone(),
+            })
+            .collect(),
+        stats: GameStats {
+            kills: stats.kills,
+            kill_income: stats.kill_income,
+            farm_income: stats.farm_income,
+            waves_cleared: stats.waves_cleared,
+        },
+    };
+    //Entity ids only have to be unique within the file, none of the real ones can be u32::MAX
+    let mut entities = vec![DynamicEntity {
+        entity: u32::MAX,
+        components: vec![Box::new(saved)],
+    }];
+
+    let players: Vec<Entity> = world
+        .query_filtered::<Entity, With<Player>>()
+        .iter(world)
+        .collect();
+    for entity in players {
+        entities.push(capture!(world, entity, Player));
+    }
+
+    let towers: Vec<Entity> = world
+        .query_filtered::<Entity, With<Tower>>()
+        .iter(world)
+        .collect();
+    for entity in towers {
+        entities.push(capture!(
+            world,
+            entity,
+            Transform,
+            TowerType,
+            Tower,
+            TowerAbility,
+            Farm
+        ));
+    }
+
+    let targets: Vec<Entity> = world
+        .query_filtered::<Entity, With<Target>>()
+        .iter(world)
+        .collect();
+    for entity in targets {
+        entities.push(capture!(
+            world,
+            entity,
+            Transform,
+            EnemyType,
+            Target,
+            Health,
+            Knockback,
+            Slowed,
+            Shield,
+            SpeedBuff,
+            Boss,
+            Invulnerable,
+            Revealed
+        ));
+    }
+
+    let scene = DynamicScene { entities };
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    if quicksave {
+        let notice = match write_save(&scene, &registry, SAVE_PATH) {
+            Ok(()) => NoticeEvent::info("Game saved"),
+            Err(err) => {
+                error!("Failed to save game: {}", err);
+                NoticeEvent::error(format!("Can't save game: {}", err))
+            }
+        };
+        world.send_event(notice);
+    }
+    if autosave {
+        //Quiet unless it goes wrong, it happens every wave
+        if let Err(err) = write_autosave(&scene, &registry) {
+            error!("Failed to autosave: {}", err);
+            world.send_event(NoticeEvent::error(format!("Can't autosave: {}", err)));
+        }
+    }
+}
+
+/// Puts saved components on an entity, replacing the ones it was spawned with
+fn restore_components(commands: &mut Commands, entity: Entity, saved: &DynamicEntity) {
+    let components: Vec<Box<dyn Reflect>> = saved
+        .components
+        .iter()
+        .map(|component| component.clone_value())
+        .collect();
+    commands.add(move |world: &mut World| {
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        for component in components {
+            //Types like `TowerType` were only needed to pick what to spawn
+            if let Some(reflect_component) = registry
+                .get_with_name(component.type_name())
+                .and_then(|registration| registration.data::<ReflectComponent>())
+            {
+                reflect_component.insert(world, entity, &*component);
+            }
+        }
+    });
+}
+
+// Runs on the frame gameplay starts, before any tick, on top of the freshly spawned level
+#[allow(clippy::too_many_arguments)]
+fn restore_game(
+    mut commands: Commands,
+    mut saves: ResMut<SaveGames>,
+    bases: Query<(Entity, &Transform), With<TowerBase>>,
+    players: Query<Entity, With<Player>>,
+    assets: Res<GameAssets>,
+    colliders: Res<SelectionColliders>,
+    mut wave_state: ResMut<WaveState>,
+    mut spellbook: ResMut<Spellbook>,
+    mut stats: ResMut<GameStats>,
+    mut rng: ResMut<SimulationRng>,
+    mut game_time: ResMut<GameTime>,
+) {
+    let Some(scene) = saves.pending.take() else {
+        return;
+    };
+    let Some(saved) = saved_game(&scene) else {
+        return;
+    };
+
+    game_time.tick = saved.tick;
+    *rng = SimulationRng::new(saved.rng);
+    wave_state.wave = saved.wave;
+    wave_state.in_progress = saved.wave_in_progress;
+    wave_state.remaining = saved.remaining.into_iter().collect();
+    wave_state.spawn_timer = saved.spawn_timer;
+    wave_state.break_timer = saved.break_timer;
+    spellbook.cooldowns = saved
+        .spell_cooldowns
+        .into_iter()
+        .map(|cooldown| (cooldown.spell, cooldown.timer))
+        .collect();
+    *stats = saved.stats;
+
+    for entity in &scene.entities {
+        let transform = find_component::<Transform>(entity).and_then(Transform::from_reflect);
+
+        if find_component::<Player>(entity).is_some() {
+            for player in &players {
+                restore_components(&mut commands, player, entity);
+            }
+        } else if let (Some(tower_type), Some(transform)) = (
+            find_component::<TowerType>(entity).and_then(TowerType::from_reflect),
+            transform,
+        ) {
+            let position = transform.translation;
+            if let Some(base) = entity_at(position, &bases) {
+                commands.entity(base).despawn_recursive();
+            }
+            let tower = spawn_tower(&mut commands, &assets, &colliders, position, tower_type, 0);
+            restore_components(&mut commands, tower, entity);
+            //Auras aren't saved, they follow from the tier
+            let mut saved_tower = Tower::default();
+            if let Some(component) = find_component::<Tower>(entity) {
+                saved_tower.apply(component);
+            }
+            if let Some(aura) = tower_type.get_aura(saved_tower.tier) {
+                commands.entity(tower).insert(aura);
+            }
+        } else if let (Some(enemy_type), Some(transform)) = (
+            find_component::<EnemyType>(entity).and_then(EnemyType::from_reflect),
+            transform,
+        ) {
+            let target = spawn_target(
+                &mut commands,
+                &assets,
+                enemy_type,
+                transform.translation,
+                0,
+                0.0,
+            );
+            restore_components(&mut commands, target, entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::Stopwatch;
+
+    use super::*;
+
+    /// Every type the fixtures use, the game registers these through its plugins
+    fn test_registry() -> TypeRegistryArc {
+        let registry = TypeRegistryArc::default();
+        {
+            let mut registry = registry.write();
+            registry.register::<SavedGame>();
+            registry.register::<SpellCooldown>();
+            registry.register::<Vec<SpellCooldown>>();
+            registry.register::<Vec<EnemyType>>();
+            registry.register::<EnemyType>();
+            registry.register::<SpellType>();
+            registry.register::<GameStats>();
+            registry.register::<Timer>();
+            registry.register::<Stopwatch>();
+            registry.register::<Duration>();
+            registry.register::<TimerMode>();
+            registry.register::<Player>();
+            registry.register::<Transform>();
+            registry.register::<Vec3>();
+            registry.register::<Quat>();
+            registry.register::<TowerType>();
+            registry.register::<Tower>();
+            registry.register::<TargetLayers>();
+            registry.register::<TargetPriority>();
+            registry.register::<TowerBuffs>();
+            registry.register::<Target>();
+            registry.register::<Health>();
+        }
+        registry
+    }
+
+    fn check_fixture_scene(scene: &DynamicScene) {
+        let saved = saved_game(scene).unwrap();
+        assert_eq!(saved.tick, 1200);
+        assert_eq!(saved.wave, 2);
+        assert_eq!(saved.remaining, vec![EnemyType::Basic, EnemyType::Flyer]);
+        assert_eq!(scene.entities.len(), 4);
+        let player = scene
+            .entities
+            .iter()
+            .find_map(|entity| find_component::<Player>(entity))
+            .unwrap();
+        let mut restored = Player::default();
+        restored.apply(player);
+        assert_eq!(restored.money, 135);
+        assert_eq!(restored.health, 9);
+    }
+
+    #[test]
+    fn loads_version_1_save() {
+        let scene = load_save(fixture("save_v1.ron"), &test_registry()).unwrap();
+        check_fixture_scene(&scene);
+    }
+
+    #[test]
+    fn loads_version_2_save() {
+        let scene = load_save(fixture("save_v2.ron"), &test_registry()).unwrap();
+        check_fixture_scene(&scene);
+    }
+
+    #[test]
+    fn wrapped_version_1_save_matches_version_2() {
+        let v1 = load_save(fixture("save_v1.ron"), &test_registry()).unwrap();
+        let v2 = load_save(fixture("save_v2.ron"), &test_registry()).unwrap();
+        let registry = test_registry();
+        assert_eq!(
+            v1.serialize_ron(&registry).unwrap(),
+            v2.serialize_ron(&registry).unwrap()
+        );
+    }
+}
@@ -0,0 +1,571 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{ui::FocusPolicy, window::WindowMode};
+use bevy_inspector_egui::WorldInspectorParams;
+use ron::ser::PrettyConfig;
+
+use crate::*;
+
+/// Bumped whenever `Settings` changes, with a step added to `SETTINGS_MIGRATIONS`
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Every step from the first settings format to the current one, see `upgrade`
+const SETTINGS_MIGRATIONS: &[Migration] = &[];
+
+const RESOLUTIONS: [(f32, f32); 4] = [
+    (WIDTH, HEIGHT),
+    (1600.0, 900.0),
+    (1920.0, 1080.0),
+    (2560.0, 1440.0),
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayMode {
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl DisplayMode {
+    const ALL: [DisplayMode; 3] = [
+        DisplayMode::Windowed,
+        DisplayMode::Borderless,
+        DisplayMode::Fullscreen,
+    ];
+
+    pub fn window_mode(&self) -> WindowMode {
+        match self {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Borderless => WindowMode::BorderlessFullscreen,
+            DisplayMode::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+/// Player preferences, kept in the user's config directory between runs.
+/// Missing fields fall back to their defaults so adding one doesn't need a migration.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    /// 0 to 1
+    pub volume: f32,
+    /// Multiplies camera movement and rotation speed
+    pub camera_sensitivity: f32,
+    pub display_mode: DisplayMode,
+    /// Window size while windowed
+    pub resolution: (f32, f32),
+    pub ui_scale: f64,
+    pub show_inspector: bool,
+    /// Only the bindings that differ from the defaults
+    pub input_map: InputMap,
+    /// Saved camera views for each level by name, then by bookmark slot
+    pub camera_bookmarks: BTreeMap<String, BTreeMap<usize, CameraBookmark>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SETTINGS_VERSION,
+            volume: 0.8,
+            camera_sensitivity: 1.0,
+            display_mode: DisplayMode::Windowed,
+            resolution: (WIDTH, HEIGHT),
+            ui_scale: 1.0,
+            show_inspector: true,
+            input_map: InputMap::default(),
+            camera_bookmarks: BTreeMap::new(),
+        }
+    }
+}
+
+/// The settings that can be changed from the settings screen, one row each
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+    Volume,
+    CameraSensitivity,
+    DisplayMode,
+    Resolution,
+    UiScale,
+    Inspector,
+}
+
+impl Setting {
+    const ALL: [Setting; 6] = [
+        Setting::Volume,
+        Setting::CameraSensitivity,
+        Setting::DisplayMode,
+        Setting::Resolution,
+        Setting::UiScale,
+        Setting::Inspector,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Setting::Volume => "Volume",
+            Setting::CameraSensitivity => "Camera sensitivity",
+            Setting::DisplayMode => "Window mode",
+            Setting::Resolution => "Resolution",
+            Setting::UiScale => "UI scale",
+            Setting::Inspector => "Inspector",
+        }
+    }
+}
+
+/// Moves to the next or previous option in a list, staying at the ends
+fn step_through<T: PartialEq + Copy>(options: &[T], current: T, step: i32) -> T {
+    let index = options
+        .iter()
+        .position(|option| *option == current)
+        .unwrap_or(0) as i32;
+    options[(index + step).clamp(0, options.len() as i32 - 1) as usize]
+}
+
+impl Settings {
+    /// `settings.ron` in the user's config directory
+    pub fn path() -> PathBuf {
+        let config_dir = if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+            PathBuf::from(dir)
+        } else if let Some(dir) = env::var_os("APPDATA") {
+            PathBuf::from(dir)
+        } else if let Some(home) = env::var_os("HOME") {
+            if cfg!(target_os = "macos") {
+                PathBuf::from(home).join("Library/Application Support")
+            } else {
+                PathBuf::from(home).join(".config")
+            }
+        } else {
+            PathBuf::from(".")
+        };
+        config_dir.join("bevy-tower-defense").join("settings.ron")
+    }
+
+    /// Defaults when there is no settings file yet, an error when there is one but it can't be read
+    pub fn load() -> Result<Self, String> {
+        Self::load_from(Self::path())
+    }
+
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Settings::default());
+        }
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let text = upgrade(text, SETTINGS_VERSION, SETTINGS_MIGRATIONS)?;
+        ron::from_str(&text).map_err(|err| err.to_string())
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let settings = Settings {
+            version: SETTINGS_VERSION,
+            ..self.clone()
+        };
+        let text = ron::ser::to_string_pretty(&settings, PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| err.to_string())
+    }
+
+    pub fn window_descriptor(&self) -> WindowDescriptor {
+        WindowDescriptor {
+            width: self.resolution.0,
+            height: self.resolution.1,
+            mode: self.display_mode.window_mode(),
+            title: "Bevy Tower Defense".to_string(),
+            resizable: false,
+            ..default()
+        }
+    }
+
+    /// Changes a setting by one step up or down
+    pub fn step(&mut self, setting: Setting, step: i32) {
+        match setting {
+            Setting::Volume => {
+                self.volume = (self.volume + 0.1 * step as f32).clamp(0.0, 1.0);
+            }
+            Setting::CameraSensitivity => {
+                self.camera_sensitivity =
+                    (self.camera_sensitivity + 0.25 * step as f32).clamp(0.25, 3.0);
+            }
+            Setting::DisplayMode => {
+                self.display_mode = step_through(&DisplayMode::ALL, self.display_mode, step);
+            }
+            Setting::Resolution => {
+                self.resolution = step_through(&RESOLUTIONS, self.resolution, step);
+            }
+            Setting::UiScale => {
+                self.ui_scale = (self.ui_scale + 0.25 * step as f64).clamp(0.5, 2.0);
+            }
+            Setting::Inspector => self.show_inspector = step > 0,
+        }
+    }
+
+    pub fn label(&self, setting: Setting) -> String {
+        match setting {
+            Setting::Volume => format!("{:.0}%", self.volume * 100.0),
+            Setting::CameraSensitivity => format!("{:.2}x", self.camera_sensitivity),
+            Setting::DisplayMode => format!("{:?}", self.display_mode),
+            Setting::Resolution => format!("{}x{}", self.resolution.0, self.resolution.1),
+            Setting::UiScale => format!("{:.2}x", self.ui_scale),
+            Setting::Inspector => if self.show_inspector { "On" } else { "Off" }.to_string(),
+        }
+    }
+}
+
+/// Opens the settings screen, from the main menu or during a game
+#[derive(Component)]
+pub struct OpenSettingsButton;
+
+#[derive(Component)]
+pub struct SettingsMenu;
+
+#[derive(Component)]
+pub enum SettingsButton {
+    Step(Setting, i32),
+    Controls,
+    Close,
+}
+
+#[derive(Component)]
+pub struct SettingsValue(pub Setting);
+
+/// The settings screen and the controls screen opened from it
+type OpenMenus<'w, 's> = Query<'w, 's, Entity, Or<(With<SettingsMenu>, With<ControlsMenu>)>>;
+
+/// Settings as read when the game started, the window is created from them before any system runs
+pub struct SettingsPlugin {
+    pub loaded: Result<Settings, String>,
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.loaded.clone().unwrap_or_default())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Gameplay).with_system(spawn_settings_button),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Gameplay).with_system(pause_hotkey))
+            .add_system(open_settings)
+            .add_system(settings_buttons_clicked)
+            .add_system(update_settings_menu)
+            .add_system(apply_settings);
+        //Broken settings file, tell the player and carry on with the defaults
+        if let Err(err) = self.loaded.clone() {
+            app.add_startup_system(move |mut notices: EventWriter<NoticeEvent>| {
+                error!("Failed to load settings: {}", err);
+                notices.send(NoticeEvent::error(format!(
+                    "Can't load settings, using defaults: {}",
+                    err
+                )));
+            });
+        }
+    }
+}
+
+fn apply_settings(
+    settings: Res<Settings>,
+    mut windows: ResMut<Windows>,
+    mut ui_scale: ResMut<UiScale>,
+    mut inspector: ResMut<WorldInspectorParams>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        if window.mode() != settings.display_mode.window_mode() {
+            window.set_mode(settings.display_mode.window_mode());
+        }
+        if (window.requested_width(), window.requested_height()) != settings.resolution {
+            window.set_resolution(settings.resolution.0, settings.resolution.1);
+        }
+    }
+    ui_scale.scale = settings.ui_scale;
+    inspector.enabled = settings.show_inspector;
+}
+
+fn spawn_settings_button(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(100.0), Val::Px(30.0)),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Percent(1.0),
+                    bottom: Val::Percent(2.0),
+                    ..default()
+                },
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(OpenSettingsButton)
+        .insert(Name::new("SettingsButton"))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section(
+                "Settings",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 20.0,
+                    color: Color::BLACK,
+                },
+            ));
+        });
+}
+
+fn open_settings(
+    mut commands: Commands,
+    interactions: Query<&Interaction, (With<OpenSettingsButton>, Changed<Interaction>)>,
+    menus: Query<(), With<SettingsMenu>>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    mut game_time: ResMut<GameTime>,
+) {
+    let clicked = interactions
+        .iter()
+        .any(|interaction| matches!(interaction, Interaction::Clicked));
+    if !clicked || !menus.is_empty() {
+        return;
+    }
+    //Doubles as the pause menu
+    game_time.paused = true;
+    spawn_settings_menu(&mut commands, &asset_server, &settings);
+}
+
+fn pause_hotkey(
+    mut commands: Commands,
+    input: ActionInput,
+    menus: OpenMenus,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    mut game_time: ResMut<GameTime>,
+    mut notices: EventWriter<NoticeEvent>,
+) {
+    if !input.just_pressed(InputAction::Pause) {
+        return;
+    }
+    if menus.is_empty() {
+        game_time.paused = true;
+        spawn_settings_menu(&mut commands, &asset_server, &settings);
+    } else {
+        close_settings_menu(
+            &mut commands,
+            &menus,
+            &settings,
+            &mut game_time,
+            &mut notices,
+        );
+    }
+}
+
+/// Closes the settings and controls screens, unpauses and keeps whatever was changed
+fn close_settings_menu(
+    commands: &mut Commands,
+    menus: &OpenMenus,
+    settings: &Settings,
+    game_time: &mut GameTime,
+    notices: &mut EventWriter<NoticeEvent>,
+) {
+    for menu in menus {
+        commands.entity(menu).despawn_recursive();
+    }
+    game_time.paused = false;
+    if let Err(err) = settings.save() {
+        error!("Failed to save settings: {}", err);
+        notices.send(NoticeEvent::error(format!("Can't save settings: {}", err)));
+    }
+}
+
+fn spawn_settings_menu(commands: &mut Commands, asset_server: &AssetServer, settings: &Settings) {
+    let text_style = TextStyle {
+        font: asset_server.load("FiraSans-Bold.ttf"),
+        font_size: 28.0,
+        color: Color::WHITE,
+    };
+    let button_style = Style {
+        size: Size::new(Val::Px(40.0), Val::Px(36.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        margin: UiRect::all(Val::Px(4.0)),
+        ..default()
+    };
+    let button_text_style = TextStyle {
+        color: Color::BLACK,
+        ..text_style.clone()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        // Keeps clicks from reaching the buttons underneath
+        .insert(Interaction::default())
+        .insert(FocusPolicy::Block)
+        .insert(SettingsMenu)
+        .insert(Name::new("SettingsMenu"))
+        .with_children(|commands| {
+            for setting in Setting::ALL {
+                commands
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(260.0), Val::Auto),
+                                ..default()
+                            },
+                            text: Text::from_section(setting.name(), text_style.clone()),
+                            ..default()
+                        });
+                        for (step, label) in [(-1, "-"), (1, "+")] {
+                            commands
+                                .spawn(ButtonBundle {
+                                    style: button_style.clone(),
+                                    ..default()
+                                })
+                                .insert(SettingsButton::Step(setting, step))
+                                .with_children(|commands| {
+                                    commands.spawn(TextBundle::from_section(
+                                        label,
+                                        button_text_style.clone(),
+                                    ));
+                                });
+                            //Value sits between the buttons
+                            if step < 0 {
+                                commands
+                                    .spawn(TextBundle {
+                                        style: Style {
+                                            size: Size::new(Val::Px(180.0), Val::Auto),
+                                            ..default()
+                                        },
+                                        text: Text::from_section(
+                                            settings.label(setting),
+                                            text_style.clone(),
+                                        )
+                                        .with_alignment(TextAlignment::CENTER),
+                                        ..default()
+                                    })
+                                    .insert(SettingsValue(setting));
+                            }
+                        }
+                    });
+            }
+            commands
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(160.0), Val::Px(44.0)),
+                        margin: UiRect::top(Val::Px(24.0)),
+                        ..button_style.clone()
+                    },
+                    background_color: Color::GRAY.into(),
+                    ..default()
+                })
+                .insert(SettingsButton::Controls)
+                .with_children(|commands| {
+                    commands.spawn(TextBundle::from_section(
+                        "Controls",
+                        button_text_style.clone(),
+                    ));
+                });
+            commands
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(160.0), Val::Px(44.0)),
+                        margin: UiRect::top(Val::Px(8.0)),
+                        ..button_style.clone()
+                    },
+                    background_color: Color::ORANGE.into(),
+                    ..default()
+                })
+                .insert(SettingsButton::Close)
+                .with_children(|commands| {
+                    commands.spawn(TextBundle::from_section("Back", button_text_style.clone()));
+                });
+        });
+}
+
+fn settings_buttons_clicked(
+    mut commands: Commands,
+    interactions: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+    menus: OpenMenus,
+    asset_server: Res<AssetServer>,
+    mut settings: ResMut<Settings>,
+    mut game_time: ResMut<GameTime>,
+    mut notices: EventWriter<NoticeEvent>,
+) {
+    for (interaction, button) in &interactions {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+        match button {
+            SettingsButton::Step(setting, step) => settings.step(*setting, *step),
+            SettingsButton::Controls => {
+                spawn_controls_menu(&mut commands, &asset_server, &settings);
+            }
+            SettingsButton::Close => {
+                close_settings_menu(
+                    &mut commands,
+                    &menus,
+                    &settings,
+                    &mut game_time,
+                    &mut notices,
+                );
+            }
+        }
+    }
+}
+
+fn update_settings_menu(settings: Res<Settings>, mut values: Query<(&SettingsValue, &mut Text)>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (value, mut text) in &mut values {
+        text.sections[0].value = settings.label(value.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_version_1_settings() {
+        let settings = Settings::load_from(fixture("settings_v1.ron")).unwrap();
+        assert_eq!(settings.volume, 0.5);
+        assert_eq!(settings.camera_sensitivity, 1.25);
+        assert_eq!(settings.display_mode, DisplayMode::Borderless);
+        assert_eq!(settings.resolution, (1600.0, 900.0));
+        //Added without a version bump, older files get the defaults
+        assert_eq!(settings.input_map, InputMap::default());
+        assert!(settings.camera_bookmarks.is_empty());
+    }
+
+    #[test]
+    fn missing_settings_file_gives_defaults() {
+        let settings = Settings::load_from(fixture("no_such_settings.ron")).unwrap();
+        assert_eq!(settings, Settings::default());
+    }
+}
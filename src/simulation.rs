@@ -0,0 +1,117 @@
+use bevy::ecs::event::Event;
+
+use crate::*;
+
+/// Runs after `CoreStage::Update` once per simulation tick, see `run_simulation_ticks`
+#[derive(StageLabel)]
+pub struct SimulationStage;
+
+/// Every gameplay system belongs to one of these, they run in this order each tick
+#[derive(SystemLabel, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SimulationStep {
+    /// Player commands queued since the last tick, or the ones from a replay
+    Input,
+    /// Carrying out the player commands
+    Actions,
+    Spawn,
+    /// Buffs, shields, detection and other status effects
+    Status,
+    Move,
+    /// Towers picking targets and firing
+    Attack,
+    Collide,
+    Damage,
+    Death,
+    Economy,
+}
+
+impl SimulationStep {
+    const ORDER: [SimulationStep; 10] = [
+        SimulationStep::Input,
+        SimulationStep::Actions,
+        SimulationStep::Spawn,
+        SimulationStep::Status,
+        SimulationStep::Move,
+        SimulationStep::Attack,
+        SimulationStep::Collide,
+        SimulationStep::Damage,
+        SimulationStep::Death,
+        SimulationStep::Economy,
+    ];
+
+    /// System set for this step, already ordered after the step before it
+    pub fn set(self) -> SystemSet {
+        let set = SystemSet::new().label(self);
+        match Self::ORDER.iter().position(|step| *step == self) {
+            Some(index) if index > 0 => set.after(Self::ORDER[index - 1]),
+            _ => set,
+        }
+    }
+}
+
+/// The only source of randomness the simulation is allowed to use
+#[derive(Resource)]
+pub struct SimulationRng {
+    state: u64,
+}
+
+impl SimulationRng {
+    pub fn new(seed: u64) -> Self {
+        SimulationRng {
+            //Zero would get stuck on zero forever
+            state: seed.max(1),
+        }
+    }
+
+    //xorshift64*, plenty for gameplay and the same on every machine
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Where the sequence is at, `new` with this carries on from the same spot
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+pub trait SimulationAppExt {
+    /// Like `add_event` but the events are kept for two simulation ticks instead of two frames,
+    /// so nothing is lost on frames where no tick runs or when several ticks run in one frame
+    fn add_simulation_event<T: Event>(&mut self) -> &mut Self;
+}
+
+impl SimulationAppExt for App {
+    fn add_simulation_event<T: Event>(&mut self) -> &mut Self {
+        self.init_resource::<Events<T>>().add_system_to_stage(
+            SimulationStage,
+            Events::<T>::update_system.before(SimulationStep::Input),
+        )
+    }
+}
+
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        // Single threaded so systems in the same step always run in the same order
+        app.add_stage_after(
+            CoreStage::Update,
+            SimulationStage,
+            SystemStage::single_threaded().with_run_criteria(run_simulation_ticks),
+        )
+        .insert_resource(SimulationRng::new(0))
+        .add_system_set(SystemSet::on_enter(GameState::Gameplay).with_system(reset_rng));
+    }
+}
+
+fn reset_rng(mut rng: ResMut<SimulationRng>, level: Res<Level>) {
+    *rng = SimulationRng::new(level.simulation_seed);
+}
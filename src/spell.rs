@@ -0,0 +1,398 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{math::Vec3Swizzles, utils::HashMap};
+
+use crate::*;
+
+/// Player abilities that don't belong to any tower
+#[derive(Reflect, FromReflect, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SpellType {
+    /// Heavy damage in an area at the cursor
+    Meteor,
+    /// Stops every enemy on the map for a moment
+    Freeze,
+    /// Emergency money, paid for with health
+    Windfall,
+}
+
+pub struct SpellCost {
+    pub money: u32,
+    pub health: u32,
+}
+
+#[derive(Resource, Default)]
+pub struct Spellbook {
+    pub cooldowns: HashMap<SpellType, Timer>,
+    /// Spell waiting for the player to pick a spot on the ground
+    pub targeting: Option<SpellType>,
+}
+
+#[derive(Component)]
+pub struct SpellButton(pub SpellType);
+
+#[derive(Component)]
+pub struct SpellPreview;
+
+pub struct SpellPlugin;
+
+impl Plugin for SpellPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SpellType>()
+            .init_resource::<Spellbook>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Gameplay)
+                    .with_system(reset_spellbook)
+                    .with_system(spawn_spell_bar)
+                    .with_system(spawn_spell_preview),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Gameplay)
+                    // Before the buttons so the click that picks a spell doesn't also cast it
+                    .with_system(cast_targeted_spell.before(spell_button_clicked))
+                    .with_system(spell_button_clicked)
+                    .with_system(update_spell_preview)
+                    .with_system(update_spell_bar),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Actions.set().with_system(cast_spells),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Status
+                    .set()
+                    .with_system(tick_spell_cooldowns),
+            );
+    }
+}
+
+impl SpellType {
+    pub const ALL: [SpellType; 3] = [SpellType::Meteor, SpellType::Freeze, SpellType::Windfall];
+
+    pub fn cost(&self) -> SpellCost {
+        match self {
+            SpellType::Meteor => SpellCost {
+                money: 40,
+                health: 0,
+            },
+            SpellType::Freeze => SpellCost {
+                money: 60,
+                health: 0,
+            },
+            SpellType::Windfall => SpellCost {
+                money: 0,
+                health: 2,
+            },
+        }
+    }
+
+    pub fn cooldown_seconds(&self) -> f32 {
+        match self {
+            SpellType::Meteor => 15.0,
+            SpellType::Freeze => 30.0,
+            SpellType::Windfall => 60.0,
+        }
+    }
+
+    /// Spells with a radius are aimed at the ground, the rest are cast right away
+    pub fn radius(&self) -> Option<f32> {
+        match self {
+            SpellType::Meteor => Some(1.5),
+            SpellType::Freeze | SpellType::Windfall => None,
+        }
+    }
+
+    fn label(&self) -> String {
+        let cost = self.cost();
+        if cost.health > 0 {
+            format!("{:?} ({} hp)", self, cost.health)
+        } else {
+            format!("{:?} ({})", self, cost.money)
+        }
+    }
+}
+
+impl Spellbook {
+    pub fn ready(&self, spell: SpellType) -> bool {
+        self.cooldowns
+            .get(&spell)
+            .is_none_or(|cooldown| cooldown.finished())
+    }
+}
+
+fn reset_spellbook(mut spellbook: ResMut<Spellbook>) {
+    *spellbook = Spellbook::default();
+}
+
+fn tick_spell_cooldowns(mut spellbook: ResMut<Spellbook>, time: Res<GameTime>) {
+    for cooldown in spellbook.cooldowns.values_mut() {
+        cooldown.tick(time.delta());
+    }
+}
+
+fn spawn_spell_bar(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let text_style = TextStyle {
+        font: asset_server.load("FiraSans-Bold.ttf"),
+        font_size: 20.0,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(1.0),
+                    bottom: Val::Percent(2.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Name::new("SpellBar"))
+        .with_children(|commands| {
+            for spell in SpellType::ALL {
+                commands
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(160.0), Val::Px(36.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            margin: UiRect::top(Val::Px(6.0)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .insert(SpellButton(spell))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(spell.label(), text_style.clone()));
+                    });
+            }
+        });
+}
+
+fn spawn_spell_preview(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let material = StandardMaterial {
+        base_color: Color::rgba(1.0, 0.4, 0.1, 0.35),
+        alpha_mode: AlphaMode::Blend,
+        unlit: true,
+        ..default()
+    };
+    commands
+        .spawn(PbrBundle {
+            mesh: meshes.add(
+                shape::Circle {
+                    radius: 1.0,
+                    vertices: 48,
+                }
+                .into(),
+            ),
+            material: materials.add(material),
+            visibility: Visibility::INVISIBLE,
+            ..default()
+        })
+        .insert(SpellPreview)
+        .insert(Name::new("SpellPreview"));
+}
+
+/// Where the cursor ray hits the ground plane
+fn cursor_ground_position(
+    windows: &Windows,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec3> {
+    let cursor = windows.get_primary()?.cursor_position()?;
+    let ray = camera.viewport_to_world(camera_transform, cursor)?;
+    if ray.direction.y >= 0.0 {
+        return None;
+    }
+    let distance = -ray.origin.y / ray.direction.y;
+    Some(ray.origin + ray.direction * distance)
+}
+
+fn spell_button_clicked(
+    interactions: Query<(&Interaction, &SpellButton), Changed<Interaction>>,
+    mut spellbook: ResMut<Spellbook>,
+    mut actions: EventWriter<PlayerActionRequest>,
+) {
+    for (interaction, button) in &interactions {
+        if !matches!(interaction, Interaction::Clicked) {
+            continue;
+        }
+        let spell = button.0;
+        if spell.radius().is_some() {
+            //Clicking the same spell again cancels the targeting
+            spellbook.targeting = if spellbook.targeting == Some(spell) {
+                None
+            } else {
+                Some(spell)
+            };
+            continue;
+        }
+        actions.send(PlayerActionRequest(PlayerAction::CastSpell {
+            spell,
+            position: None,
+        }));
+    }
+}
+
+/// Takes the cost and starts the cooldown, false if the spell can't be cast right now
+fn try_pay(spell: SpellType, spellbook: &mut Spellbook, player: &mut Player) -> bool {
+    let cost = spell.cost();
+    //Never let a spell kill the player
+    if !spellbook.ready(spell) || player.money < cost.money || player.health <= cost.health {
+        return false;
+    }
+    player.money -= cost.money;
+    player.health -= cost.health;
+    spellbook.cooldowns.insert(
+        spell,
+        Timer::from_seconds(spell.cooldown_seconds(), TimerMode::Once),
+    );
+    true
+}
+
+fn cast_targeted_spell(
+    mouse: Res<Input<MouseButton>>,
+    input: ActionInput,
+    windows: Res<Windows>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut spellbook: ResMut<Spellbook>,
+    buttons: Query<&Interaction, With<Button>>,
+    mut actions: EventWriter<PlayerActionRequest>,
+) {
+    let Some(spell) = spellbook.targeting else {
+        return;
+    };
+    if input.just_pressed(InputAction::CancelSpell) {
+        spellbook.targeting = None;
+        return;
+    }
+    //Clicks on the UI are for the UI, not the ground behind it
+    let over_ui = buttons
+        .iter()
+        .any(|interaction| !matches!(interaction, Interaction::None));
+    if !mouse.just_pressed(MouseButton::Left) || over_ui {
+        return;
+    }
+
+    let (camera, camera_transform) = camera.single();
+    let Some(position) = cursor_ground_position(&windows, camera, camera_transform) else {
+        return;
+    };
+    spellbook.targeting = None;
+    actions.send(PlayerActionRequest(PlayerAction::CastSpell {
+        spell,
+        position: Some(position),
+    }));
+}
+
+fn cast_spells(
+    mut commands: Commands,
+    mut actions: EventReader<PlayerActionEvent>,
+    mut spellbook: ResMut<Spellbook>,
+    mut player: Query<&mut Player>,
+    targets: Query<(Entity, &Transform), With<Target>>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    let mut player = player.single_mut();
+    for PlayerActionEvent(action) in actions.iter() {
+        let PlayerAction::CastSpell { spell, position } = action else {
+            continue;
+        };
+        if spell.radius().is_some() != position.is_some()
+            || !try_pay(*spell, &mut spellbook, &mut player)
+        {
+            continue;
+        }
+
+        match (spell, position) {
+            (SpellType::Meteor, Some(position)) => {
+                let radius = spell.radius().unwrap_or_default();
+                for (target, transform) in &targets {
+                    if transform.translation.xz().distance(position.xz()) < radius {
+                        damage_events.send(DamageEvent {
+                            target,
+                            amount: 5,
+                            damage_type: DamageType::Physical,
+                            tower_type: None,
+                            knockback: 0.0,
+                        });
+                    }
+                }
+            }
+            (SpellType::Freeze, _) => {
+                for (target, _) in &targets {
+                    commands.entity(target).insert(Slowed {
+                        multiplier: 0.0,
+                        timer: Timer::from_seconds(3.0, TimerMode::Once),
+                    });
+                }
+            }
+            (SpellType::Windfall, _) => player.money += 100,
+            (SpellType::Meteor, None) => unreachable!("Meteor is aimed at the ground"),
+        }
+    }
+}
+
+fn update_spell_preview(
+    spellbook: Res<Spellbook>,
+    windows: Res<Windows>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut preview: Query<(&mut Transform, &mut Visibility), With<SpellPreview>>,
+) {
+    let (camera, camera_transform) = camera.single();
+    let position = cursor_ground_position(&windows, camera, camera_transform);
+    for (mut transform, mut visibility) in &mut preview {
+        let radius = spellbook.targeting.and_then(|spell| spell.radius());
+        match (radius, position) {
+            (Some(radius), Some(position)) => {
+                visibility.is_visible = true;
+                //Lifted a little so it doesn't fight with the path
+                *transform = Transform::from_translation(position + Vec3::Y * 0.02)
+                    .with_rotation(Quat::from_rotation_x(-FRAC_PI_2))
+                    .with_scale(Vec3::splat(radius));
+            }
+            _ => visibility.is_visible = false,
+        }
+    }
+}
+
+fn update_spell_bar(
+    spellbook: Res<Spellbook>,
+    player: Query<&Player>,
+    mut buttons: Query<(&SpellButton, &Children, &mut BackgroundColor)>,
+    mut texts: Query<&mut Text>,
+) {
+    let player = player.single();
+    for (button, children, mut color) in &mut buttons {
+        let spell = button.0;
+        let cost = spell.cost();
+        let affordable = player.money >= cost.money && player.health > cost.health;
+        *color = if spellbook.targeting == Some(spell) {
+            Color::GOLD.into()
+        } else if spellbook.ready(spell) && affordable {
+            Color::WHITE.into()
+        } else {
+            Color::GRAY.into()
+        };
+
+        let label = match spellbook.cooldowns.get(&spell) {
+            Some(cooldown) if !cooldown.finished() => {
+                format!("{:?} ({:.0}s)", spell, cooldown.remaining_secs().ceil())
+            }
+            _ => spell.label(),
+        };
+        for child in children {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = label.clone();
+            }
+        }
+    }
+}
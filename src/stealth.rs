@@ -0,0 +1,102 @@
+use bevy::utils::HashMap;
+
+use crate::*;
+
+/// Stealthy targets are hidden from towers unless they are `Revealed`
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Stealth;
+
+/// Anything can reveal a stealthy target for a while by inserting this
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Revealed {
+    pub timer: Timer,
+}
+
+/// Towers with this reveal stealthy targets in range for every tower
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Detector {
+    pub range: f32,
+}
+
+pub struct StealthPlugin;
+
+impl Plugin for StealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Stealth>()
+            .register_type::<Revealed>()
+            .register_type::<Detector>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Gameplay).with_system(fade_stealth_models),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Status
+                    .set()
+                    .with_system(detect_stealth)
+                    .with_system(tick_revealed.after(detect_stealth)),
+            );
+    }
+}
+
+fn detect_stealth(
+    mut commands: Commands,
+    detectors: Query<(&Detector, &Transform)>,
+    targets: Query<(Entity, &Transform), With<Stealth>>,
+) {
+    for (entity, transform) in &targets {
+        let detected = detectors.iter().any(|(detector, detector_transform)| {
+            Vec3::distance(transform.translation, detector_transform.translation) < detector.range
+        });
+        if detected {
+            // Short so the target hides again soon after leaving the range
+            commands.entity(entity).insert(Revealed {
+                timer: Timer::from_seconds(0.2, TimerMode::Once),
+            });
+        }
+    }
+}
+
+fn tick_revealed(
+    mut commands: Commands,
+    mut targets: Query<(Entity, &mut Revealed)>,
+    time: Res<GameTime>,
+) {
+    for (entity, mut revealed) in &mut targets {
+        revealed.timer.tick(time.delta());
+        if revealed.timer.just_finished() {
+            commands.entity(entity).remove::<Revealed>();
+        }
+    }
+}
+
+// Scenes spawn their meshes a few frames later, so swap materials as they show up
+fn fade_stealth_models(
+    mut meshes: Query<(Entity, &mut Handle<StandardMaterial>), Added<Handle<StandardMaterial>>>,
+    parents: Query<&Parent>,
+    stealth: Query<(), With<Stealth>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut faded: Local<HashMap<Handle<StandardMaterial>, Handle<StandardMaterial>>>,
+) {
+    for (entity, mut material) in &mut meshes {
+        let is_stealthy = parents
+            .iter_ancestors(entity)
+            .any(|ancestor| stealth.contains(ancestor));
+        if !is_stealthy {
+            continue;
+        }
+
+        if let Some(faded_material) = faded.get(&*material) {
+            *material = faded_material.clone();
+        } else if let Some(original) = materials.get(&*material) {
+            let mut faded_material = original.clone();
+            faded_material.base_color.set_a(0.35);
+            faded_material.alpha_mode = AlphaMode::Blend;
+            let faded_material = materials.add(faded_material);
+            faded.insert(material.clone(), faded_material.clone());
+            *material = faded_material;
+        }
+    }
+}
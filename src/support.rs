@@ -0,0 +1,147 @@
+use crate::*;
+
+/// Marks targets that help other targets, towers can choose to shoot these first
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct SupportTarget;
+
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Healer {
+    pub radius: f32,
+    pub amount: i32,
+    pub timer: Timer,
+}
+
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Shielder {
+    pub radius: f32,
+    pub amount: i32,
+    pub duration: f32,
+    pub timer: Timer,
+}
+
+/// Absorbs damage before it reaches `Health`
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Shield {
+    pub value: i32,
+    pub timer: Timer,
+}
+
+/// Other targets in range move faster, overlapping auras don't stack and the strongest one wins
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct SpeedAura {
+    pub radius: f32,
+    pub multiplier: f32,
+}
+
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct SpeedBuff {
+    pub multiplier: f32,
+}
+
+pub struct SupportPlugin;
+
+impl Plugin for SupportPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SupportTarget>()
+            .register_type::<Healer>()
+            .register_type::<Shielder>()
+            .register_type::<Shield>()
+            .register_type::<SpeedAura>()
+            .register_type::<SpeedBuff>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Status
+                    .set()
+                    .with_system(heal_allies)
+                    .with_system(shield_allies)
+                    .with_system(tick_shields)
+                    .with_system(apply_speed_auras),
+            );
+    }
+}
+
+fn heal_allies(
+    mut healers: Query<(Entity, &mut Healer, &Transform)>,
+    mut targets: Query<(Entity, &mut Health, &Transform), With<Target>>,
+    time: Res<GameTime>,
+) {
+    for (healer_entity, mut healer, healer_transform) in &mut healers {
+        healer.timer.tick(time.delta());
+        if !healer.timer.just_finished() {
+            continue;
+        }
+
+        for (entity, mut health, transform) in &mut targets {
+            if entity != healer_entity
+                && Vec3::distance(transform.translation, healer_transform.translation)
+                    < healer.radius
+            {
+                health.value = (health.value + healer.amount).min(health.max);
+            }
+        }
+    }
+}
+
+fn shield_allies(
+    mut commands: Commands,
+    mut shielders: Query<(Entity, &mut Shielder, &Transform)>,
+    targets: Query<(Entity, &Transform, Option<&Shield>), With<Target>>,
+    time: Res<GameTime>,
+) {
+    for (shielder_entity, mut shielder, shielder_transform) in &mut shielders {
+        shielder.timer.tick(time.delta());
+        if !shielder.timer.just_finished() {
+            continue;
+        }
+
+        for (entity, transform, shield) in &targets {
+            let in_range = entity != shielder_entity
+                && Vec3::distance(transform.translation, shielder_transform.translation)
+                    < shielder.radius;
+            // Never replace a stronger shield with a weaker one
+            let stronger = shield.is_none_or(|shield| shield.value <= shielder.amount);
+            if in_range && stronger {
+                commands.entity(entity).insert(Shield {
+                    value: shielder.amount,
+                    timer: Timer::from_seconds(shielder.duration, TimerMode::Once),
+                });
+            }
+        }
+    }
+}
+
+fn tick_shields(
+    mut commands: Commands,
+    mut shields: Query<(Entity, &mut Shield)>,
+    time: Res<GameTime>,
+) {
+    for (entity, mut shield) in &mut shields {
+        shield.timer.tick(time.delta());
+        if shield.timer.finished() || shield.value <= 0 {
+            commands.entity(entity).remove::<Shield>();
+        }
+    }
+}
+
+fn apply_speed_auras(
+    auras: Query<(Entity, &SpeedAura, &Transform)>,
+    mut targets: Query<(Entity, &mut SpeedBuff, &Transform)>,
+) {
+    for (entity, mut buff, transform) in &mut targets {
+        buff.multiplier = auras
+            .iter()
+            .filter(|(aura_entity, aura, aura_transform)| {
+                *aura_entity != entity
+                    && Vec3::distance(transform.translation, aura_transform.translation)
+                        < aura.radius
+            })
+            .map(|(_, aura, _)| aura.multiplier)
+            .fold(1.0, f32::max);
+    }
+}
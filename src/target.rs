@@ -1,23 +1,234 @@
-use bevy::{math::Vec3Swizzles, prelude::*};
+use bevy::{ecs::system::EntityCommands, math::Vec3Swizzles, prelude::*};
 
-use crate::{GameState, Player};
+use crate::*;
 
 #[derive(Reflect, Component, Default)]
 #[reflect(Component)]
 pub struct Target {
     pub speed: f32,
     pub path_index: usize,
+    /// How far along the path this target is, negative while still walking to the first waypoint
+    pub path_distance: f32,
 }
 
+impl Target {
+    /// Walking speed after support buffs and slows
+    pub fn effective_speed(&self, speed_buff: &SpeedBuff, slowed: Option<&Slowed>) -> f32 {
+        let slow = slowed.map_or(1.0, |slowed| slowed.multiplier);
+        self.speed * speed_buff.multiplier * slow
+    }
+}
+
+#[derive(Reflect, FromReflect, Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub enum EnemyType {
+    #[default]
+    Basic,
+    Flyer,
+    Boss,
+    Splitter,
+    Healer,
+    Shielder,
+    Hastener,
+    Ghost,
+    Knight,
+}
+
+#[derive(Reflect, FromReflect, Clone, Default)]
+pub struct DeathSpawn {
+    pub enemy_type: EnemyType,
+    pub count: u32,
+    /// Distance along the path the children are spread over, centered on the parent
+    pub spread: f32,
+}
+
+/// Targets with this release more targets when they die
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct SpawnOnDeath {
+    pub children: Vec<DeathSpawn>,
+}
+
+/// Distance left to be pushed back along the path, capped by what the enemy type can take
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Knockback {
+    pub remaining: f32,
+    pub max: f32,
+}
+
+/// Flying targets ignore the ground path and follow the `AirPath` instead
+#[derive(Reflect, Component, Default)]
+#[reflect(Component)]
+pub struct Flying;
+
 #[derive(Reflect, Component, Default)]
 #[reflect(Component)]
 pub struct Health {
     pub value: i32,
+    pub max: i32,
+}
+
+impl Health {
+    pub fn new(value: i32) -> Self {
+        Health { value, max: value }
+    }
+}
+
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathMode {
+    /// Straight lines between waypoints
+    #[default]
+    Linear,
+    /// Smooth curve passing through every waypoint
+    CatmullRom,
 }
 
 #[derive(Resource)]
 pub struct TargetPath {
     waypoints: Vec<Vec2>,
+    //The path sampled into short straight pieces so movement can be done by distance
+    samples: Vec<Vec2>,
+    sample_distances: Vec<f32>,
+    waypoint_distances: Vec<f32>,
+}
+
+#[derive(Resource)]
+pub struct AirPath {
+    pub path: TargetPath,
+}
+
+impl AirPath {
+    /// Picks the path a target walks or flies along
+    pub fn or_ground<'a>(&'a self, ground: &'a TargetPath, flying: bool) -> &'a TargetPath {
+        if flying {
+            &self.path
+        } else {
+            ground
+        }
+    }
+}
+
+const SAMPLES_PER_SEGMENT: usize = 16;
+const TARGET_TURN_SPEED: f32 = 6.0;
+const KNOCKBACK_SPEED: f32 = 4.0;
+
+impl TargetPath {
+    pub fn new(waypoints: Vec<Vec2>, mode: PathMode) -> Self {
+        assert!(!waypoints.is_empty(), "A path needs at least one waypoint");
+
+        let mut samples = vec![waypoints[0]];
+        let mut sample_distances = vec![0.0];
+        let mut waypoint_distances = vec![0.0];
+
+        for i in 0..waypoints.len() - 1 {
+            let steps = match mode {
+                PathMode::Linear => 1,
+                PathMode::CatmullRom => SAMPLES_PER_SEGMENT,
+            };
+            for step in 1..=steps {
+                let t = step as f32 / steps as f32;
+                let point = match mode {
+                    PathMode::Linear => waypoints[i].lerp(waypoints[i + 1], t),
+                    PathMode::CatmullRom => {
+                        //Ends of the path are handled by repeating the first and last waypoint
+                        let p0 = waypoints[i.saturating_sub(1)];
+                        let p3 = waypoints[(i + 2).min(waypoints.len() - 1)];
+                        catmull_rom(p0, waypoints[i], waypoints[i + 1], p3, t)
+                    }
+                };
+                let distance = sample_distances[sample_distances.len() - 1]
+                    + Vec2::distance(samples[samples.len() - 1], point);
+                samples.push(point);
+                sample_distances.push(distance);
+            }
+            waypoint_distances.push(sample_distances[sample_distances.len() - 1]);
+        }
+
+        TargetPath {
+            waypoints,
+            samples,
+            sample_distances,
+            waypoint_distances,
+        }
+    }
+
+    pub fn waypoints(&self) -> &[Vec2] {
+        &self.waypoints
+    }
+
+    pub fn start(&self) -> Vec2 {
+        self.waypoints[0]
+    }
+
+    pub fn end(&self) -> Vec2 {
+        self.waypoints[self.waypoints.len() - 1]
+    }
+
+    pub fn samples(&self) -> &[Vec2] {
+        &self.samples
+    }
+
+    pub fn length(&self) -> f32 {
+        self.sample_distances[self.sample_distances.len() - 1]
+    }
+
+    /// Position and direction of travel at a distance along the path
+    pub fn sample(&self, distance: f32) -> (Vec2, Vec2) {
+        let distance = distance.clamp(0.0, self.length());
+        if self.samples.len() == 1 {
+            return (self.samples[0], Vec2::ZERO);
+        }
+
+        let next = self
+            .sample_distances
+            .partition_point(|sample| *sample < distance)
+            .clamp(1, self.samples.len() - 1);
+        let (start, end) = (self.samples[next - 1], self.samples[next]);
+        let piece_length = self.sample_distances[next] - self.sample_distances[next - 1];
+        let t = if piece_length > 0.0 {
+            (distance - self.sample_distances[next - 1]) / piece_length
+        } else {
+            0.0
+        };
+
+        (start.lerp(end, t), (end - start).normalize_or_zero())
+    }
+
+    /// The index of the next waypoint a target at this distance is walking towards
+    pub fn waypoint_index_at(&self, distance: f32) -> usize {
+        self.waypoint_distances
+            .partition_point(|waypoint| *waypoint <= distance)
+    }
+
+    /// Shortest distance from a point to anywhere on the path
+    pub fn distance_to(&self, point: Vec2) -> f32 {
+        if self.samples.len() == 1 {
+            return Vec2::distance(self.samples[0], point);
+        }
+
+        self.samples
+            .windows(2)
+            .map(|piece| {
+                let along = piece[1] - piece[0];
+                let t = if along.length_squared() > 0.0 {
+                    ((point - piece[0]).dot(along) / along.length_squared()).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                Vec2::distance(piece[0] + along * t, point)
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+}
+
+fn catmull_rom(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f32) -> Vec2 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
 }
 
 //Can have any data attached (i.e what kind of target or it's value)
@@ -28,85 +239,391 @@ pub struct TargetPlugin;
 impl Plugin for TargetPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Target>()
+            .register_type::<EnemyType>()
+            .register_type::<SpawnOnDeath>()
+            .register_type::<Knockback>()
+            .register_type::<Flying>()
             .register_type::<Health>()
-            .add_event::<TargetDeathEvent>()
-            //Could be loaded from a config or level file
-            .insert_resource(TargetPath {
-                waypoints: vec![
-                    Vec2::new(6.0, 2.0),
-                    Vec2::new(6.0, 6.0),
-                    Vec2::new(9.0, 9.0),
-                ],
-            })
-            .add_system_set(
-                SystemSet::on_update(GameState::Gameplay)
+            .register_type::<PathMode>()
+            .add_simulation_event::<TargetDeathEvent>()
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Move
+                    .set()
                     .with_system(move_targets)
-                    .with_system(hurt_player.after(move_targets))
-                    .with_system(target_death),
+                    .with_system(hurt_player.after(move_targets)),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Death.set().with_system(target_death),
             );
     }
 }
 
+impl EnemyType {
+    fn get_target(&self, assets: &GameAssets) -> (Handle<Scene>, Target, Health) {
+        match self {
+            EnemyType::Basic => (
+                assets.target_scene.clone(),
+                Target {
+                    speed: 0.45,
+                    ..Default::default()
+                },
+                Health::new(3),
+            ),
+            EnemyType::Flyer => (
+                assets.target_scene.clone(),
+                Target {
+                    speed: 0.6,
+                    ..Default::default()
+                },
+                Health::new(2),
+            ),
+            EnemyType::Boss => (
+                assets.target_scene.clone(),
+                Target {
+                    speed: 0.3,
+                    ..Default::default()
+                },
+                Health::new(60),
+            ),
+            EnemyType::Splitter => (
+                assets.target_scene.clone(),
+                Target {
+                    speed: 0.35,
+                    ..Default::default()
+                },
+                Health::new(6),
+            ),
+            EnemyType::Healer | EnemyType::Shielder | EnemyType::Hastener => (
+                assets.target_scene.clone(),
+                Target {
+                    speed: 0.4,
+                    ..Default::default()
+                },
+                Health::new(4),
+            ),
+            EnemyType::Ghost => (
+                assets.target_scene.clone(),
+                Target {
+                    speed: 0.5,
+                    ..Default::default()
+                },
+                Health::new(3),
+            ),
+            EnemyType::Knight => (
+                assets.target_scene.clone(),
+                Target {
+                    speed: 0.3,
+                    ..Default::default()
+                },
+                Health::new(8),
+            ),
+        }
+    }
+
+    pub fn is_flying(&self) -> bool {
+        matches!(self, EnemyType::Flyer)
+    }
+
+    pub fn is_stealthy(&self) -> bool {
+        matches!(self, EnemyType::Ghost)
+    }
+
+    pub fn scale(&self) -> f32 {
+        match self {
+            EnemyType::Boss => 2.0,
+            EnemyType::Splitter | EnemyType::Knight => 1.4,
+            _ => 1.0,
+        }
+    }
+
+    /// The most knockback that can be queued up on this kind of target at once
+    pub fn max_knockback(&self) -> f32 {
+        match self {
+            EnemyType::Boss => 0.0,
+            EnemyType::Knight => 0.3,
+            EnemyType::Splitter => 0.6,
+            _ => 1.2,
+        }
+    }
+
+    /// How high above the ground this kind of target moves
+    pub fn height(&self, level: &Level) -> f32 {
+        if self.is_flying() {
+            level.air_altitude
+        } else {
+            0.4 * self.scale()
+        }
+    }
+
+    /// Support targets help the targets around them and are marked so towers can prioritise them
+    fn add_support(&self, entity: &mut EntityCommands) {
+        match self {
+            EnemyType::Healer => {
+                entity.insert(Healer {
+                    radius: 2.5,
+                    amount: 1,
+                    timer: Timer::from_seconds(2.0, TimerMode::Repeating),
+                });
+            }
+            EnemyType::Shielder => {
+                entity.insert(Shielder {
+                    radius: 2.5,
+                    amount: 2,
+                    duration: 3.0,
+                    timer: Timer::from_seconds(4.0, TimerMode::Repeating),
+                });
+            }
+            EnemyType::Hastener => {
+                entity.insert(SpeedAura {
+                    radius: 2.0,
+                    multiplier: 1.5,
+                });
+            }
+            _ => return,
+        }
+        entity.insert(SupportTarget);
+    }
+
+    fn get_armor(&self) -> Option<Armor> {
+        match self {
+            EnemyType::Knight => Some(Armor {
+                flat: 1,
+                immune: vec![DamageSource::Tower(TowerType::Tomato)],
+                weak: vec![DamageSource::Type(DamageType::Nature)],
+            }),
+            EnemyType::Boss => Some(Armor {
+                flat: 0,
+                immune: Vec::new(),
+                weak: vec![DamageSource::Type(DamageType::Acid)],
+            }),
+            _ => None,
+        }
+    }
+
+    fn get_death_spawns(&self) -> Vec<DeathSpawn> {
+        match self {
+            EnemyType::Splitter => vec![DeathSpawn {
+                enemy_type: EnemyType::Basic,
+                count: 3,
+                spread: 1.0,
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Phases trigger once health drops to a fraction of the starting health
+    fn get_boss(&self) -> Option<Boss> {
+        match self {
+            EnemyType::Boss => Some(Boss {
+                phases: vec![
+                    BossPhase {
+                        health_fraction: 0.66,
+                        speed_multiplier: 1.5,
+                        minions: 3,
+                        invulnerable_seconds: 0.0,
+                    },
+                    BossPhase {
+                        health_fraction: 0.33,
+                        speed_multiplier: 1.3,
+                        minions: 4,
+                        invulnerable_seconds: 3.0,
+                    },
+                ],
+                next_phase: 0,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Spawns a target already part way along its path, use a path_index of 0 to start from the spawn
+pub fn spawn_target(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    enemy_type: EnemyType,
+    position: Vec3,
+    path_index: usize,
+    path_distance: f32,
+) -> Entity {
+    let (scene, mut target, health) = enemy_type.get_target(assets);
+    target.path_index = path_index;
+    target.path_distance = path_distance;
+
+    let mut entity = commands.spawn(SceneBundle {
+        scene,
+        transform: Transform::from_translation(position)
+            .with_scale(Vec3::splat(enemy_type.scale())),
+        ..Default::default()
+    });
+    entity
+        .insert(target)
+        .insert(health)
+        .insert(SpeedBuff { multiplier: 1.0 })
+        .insert(Knockback {
+            remaining: 0.0,
+            max: enemy_type.max_knockback(),
+        })
+        .insert(enemy_type)
+        .insert(Name::new(format!("{:?}_Target", enemy_type)));
+    if enemy_type.is_flying() {
+        entity.insert(Flying);
+    }
+    if enemy_type.is_stealthy() {
+        entity.insert(Stealth);
+    }
+    if let Some(armor) = enemy_type.get_armor() {
+        entity.insert(armor);
+    }
+    if let Some(boss) = enemy_type.get_boss() {
+        entity.insert(boss);
+    }
+    enemy_type.add_support(&mut entity);
+    let children = enemy_type.get_death_spawns();
+    if !children.is_empty() {
+        entity.insert(SpawnOnDeath { children });
+    }
+    entity.id()
+}
+
 fn target_death(
     mut commands: Commands,
-    targets: Query<(Entity, &Health)>,
+    targets: Query<(Entity, &Health, &Target, &Transform, Option<&SpawnOnDeath>)>,
     mut death_event_writer: EventWriter<TargetDeathEvent>,
+    assets: Res<GameAssets>,
+    level: Res<Level>,
+    mut rng: ResMut<SimulationRng>,
 ) {
-    for (ent, health) in &targets {
+    for (ent, health, target, transform, spawn_on_death) in &targets {
         if health.value <= 0 {
             death_event_writer.send(TargetDeathEvent);
             commands.entity(ent).despawn_recursive();
+
+            // Children pick up from the parent's progress instead of restarting the path
+            for death_spawn in spawn_on_death.iter().flat_map(|spawn| &spawn.children) {
+                for i in 0..death_spawn.count {
+                    let offset = if death_spawn.count > 1 {
+                        death_spawn.spread * (i as f32 / (death_spawn.count - 1) as f32 - 0.5)
+                    } else {
+                        0.0
+                    };
+                    //A little jitter so children never sit exactly on top of each other
+                    let offset = offset + (rng.next_f32() - 0.5) * 0.1;
+                    let mut position = transform.translation;
+                    position.y = death_spawn.enemy_type.height(&level);
+                    spawn_target(
+                        &mut commands,
+                        &assets,
+                        death_spawn.enemy_type,
+                        position,
+                        target.path_index,
+                        target.path_distance + offset,
+                    );
+                }
+            }
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn hurt_player(
     mut commands: Commands,
-    targets: Query<(Entity, &Target)>,
+    targets: Query<(Entity, &Target, Option<&Flying>)>,
     path: Res<TargetPath>,
+    air_path: Res<AirPath>,
     mut player: Query<&mut Player>,
     audio: Res<Audio>,
     asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    mut game_over: EventWriter<GameOverEvent>,
 ) {
-    for (entity, target) in &targets {
-        if target.path_index >= path.waypoints.len() {
+    for (entity, target, flying) in &targets {
+        let path = air_path.or_ground(&path, flying.is_some());
+        if target.path_index >= path.waypoints().len() {
             commands.entity(entity).despawn_recursive();
 
             //Enemies reaching the end of their path could write an event to cause the player to take damage or play audio
-            audio.play(asset_server.load("damage.wav"));
+            audio.play_with_settings(
+                asset_server.load("damage.wav"),
+                PlaybackSettings::ONCE.with_volume(settings.volume),
+            );
 
             let mut player = player.single_mut();
+            //Only the hit that takes the last point ends the game, later ones find it at 0
             if player.health > 0 {
                 player.health -= 1;
-            }
-
-            if player.health == 0 {
-                //TODO this could write an event or change the game state
-                info!("GAME OVER");
+                if player.health == 0 {
+                    info!("GAME OVER");
+                    game_over.send(GameOverEvent { won: false });
+                }
             }
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn move_targets(
-    mut targets: Query<(&mut Target, &mut Transform)>,
+    mut targets: Query<(
+        &mut Target,
+        &mut Transform,
+        &mut Knockback,
+        &SpeedBuff,
+        Option<&Slowed>,
+        Option<&Flying>,
+    )>,
     path: Res<TargetPath>,
-    time: Res<Time>,
+    air_path: Res<AirPath>,
+    time: Res<GameTime>,
 ) {
-    for (mut target, mut transform) in &mut targets {
-        let delta = target.speed * time.delta_seconds();
-        let delta_target = path.waypoints[target.path_index] - transform.translation.xz();
-
-        // This step will get us closer to the goal
-        if delta_target.length() > delta {
-            let movement = delta_target.normalize() * delta;
-            transform.translation += movement.extend(0.0).xzy();
-            //Copy for ownership reasons
-            let y = transform.translation.y;
-            transform.look_at(path.waypoints[target.path_index].extend(y).xzy(), Vec3::Y);
+    for (mut target, mut transform, mut knockback, speed_buff, slowed, flying) in &mut targets {
+        let path = air_path.or_ground(&path, flying.is_some());
+        let mut delta = target.effective_speed(speed_buff, slowed) * time.delta_seconds();
+        // Being knocked back replaces walking forward until it is used up
+        if knockback.remaining > 0.0 {
+            let pushed = (KNOCKBACK_SPEED * time.delta_seconds()).min(knockback.remaining);
+            knockback.remaining -= pushed;
+            delta = -pushed;
+        }
+        //Copy for ownership reasons
+        let y = transform.translation.y;
+
+        let heading = if target.path_index == 0 {
+            // Still walking from the spawn point to the start of the path
+            let delta_start = path.start() - transform.translation.xz();
+            if delta_start.length() > delta {
+                transform.translation +=
+                    (delta_start.normalize_or_zero() * delta).extend(0.0).xzy();
+                target.path_distance = delta - delta_start.length();
+                //Knocked back while standing right on the start has no direction to face
+                match delta_start.try_normalize() {
+                    Some(heading) => heading,
+                    None => path.sample(0.0).1,
+                }
+            } else {
+                transform.translation = path.start().extend(y).xzy();
+                target.path_distance = 0.0;
+                target.path_index = path.waypoint_index_at(0.0);
+                path.sample(0.0).1
+            }
         } else {
-            // At current step
-            target.path_index += 1;
+            // Movement is done by distance so speed stays constant on curves
+            target.path_distance += delta;
+            let (position, heading) = path.sample(target.path_distance);
+            // Pushed back past the start of the path, walking in from the spawn again
+            let position = position + heading * target.path_distance.min(0.0);
+            transform.translation = position.extend(y).xzy();
+            target.path_index = path.waypoint_index_at(target.path_distance);
+            heading
+        };
+
+        // Ease towards the direction of travel instead of snapping at corners
+        if heading != Vec2::ZERO {
+            let facing = transform
+                .looking_at(transform.translation + heading.extend(0.0).xzy(), Vec3::Y)
+                .rotation;
+            let turn = (TARGET_TURN_SPEED * time.delta_seconds()).min(1.0);
+            transform.rotation = transform.rotation.slerp(facing, turn);
         }
     }
 }
@@ -1,4 +1,10 @@
-use bevy::{ecs::query::QuerySingleError, prelude::*};
+use bevy::{
+    ecs::{query::QuerySingleError, system::EntityCommands},
+    math::Vec3Swizzles,
+    pbr::NotShadowCaster,
+    prelude::*,
+    utils::HashSet,
+};
 
 use crate::*;
 
@@ -8,13 +14,188 @@ pub struct Tower {
     pub shooting_timer: Timer,
     pub bullet_offset: Vec3,
     pub range: f32,
+    pub layers: TargetLayers,
+    pub priority: TargetPriority,
+    pub tier: u32,
+    /// Everything spent on building and upgrading, part of it comes back when sold
+    pub invested: u32,
+    pub buffs: TowerBuffs,
 }
 
-#[derive(Inspectable, Component, Clone, Copy, Debug)]
+/// Multipliers from nearby aura towers, the values on `Tower` are always the unbuffed ones
+#[derive(Reflect, Clone, Copy, Debug)]
+pub struct TowerBuffs {
+    pub fire_rate: f32,
+    pub range: f32,
+    pub damage: f32,
+}
+
+impl Default for TowerBuffs {
+    fn default() -> Self {
+        TowerBuffs {
+            fire_rate: 1.0,
+            range: 1.0,
+            damage: 1.0,
+        }
+    }
+}
+
+/// Everything a tower looks at when picking a target
+pub type TargetCandidates<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static Target,
+        &'static Health,
+        Option<&'static Flying>,
+        Option<&'static SupportTarget>,
+        Option<&'static Stealth>,
+        Option<&'static Revealed>,
+    ),
+>;
+
+impl Tower {
+    /// Whether a target is in range and can be seen and hit by this tower
+    pub fn can_target(&self, from: Vec3, target: Entity, targets: &TargetCandidates) -> bool {
+        let Ok((_, transform, _, _, flying, _, stealth, revealed)) = targets.get(target) else {
+            return false;
+        };
+        //Stealthy targets can only be shot once something reveals them
+        (stealth.is_none() || revealed.is_some())
+            && self.layers.can_hit(flying.is_some())
+            && Vec3::distance(transform.translation, from) < self.buffed_range()
+    }
+
+    pub fn buffed_range(&self) -> f32 {
+        self.range * self.buffs.range
+    }
+
+    /// Half of everything spent on the tower comes back
+    pub fn sell_value(&self) -> u32 {
+        self.invested / 2
+    }
+
+    pub fn buffed_damage(&self, damage: i32) -> i32 {
+        (damage as f32 * self.buffs.damage).round() as i32
+    }
+
+    /// The target this tower would most like to shoot
+    pub fn choose_target(&self, from: Vec3, targets: &TargetCandidates) -> Option<Entity> {
+        targets
+            .iter()
+            .filter(|(entity, ..)| self.can_target(from, *entity, targets))
+            .max_by_key(|(_, transform, target, health, _, support, ..)| {
+                let distance = Vec3::distance(transform.translation, from);
+                //Ties fall back to the closest target
+                match self.priority {
+                    TargetPriority::Closest => (FloatOrd(0.0), FloatOrd(-distance)),
+                    TargetPriority::First => (FloatOrd(target.path_distance), FloatOrd(-distance)),
+                    TargetPriority::Strongest => {
+                        (FloatOrd(health.value as f32), FloatOrd(-distance))
+                    }
+                    TargetPriority::Support => {
+                        let support = if support.is_some() { 1.0 } else { 0.0 };
+                        (FloatOrd(support), FloatOrd(-distance))
+                    }
+                }
+            })
+            .map(|(entity, ..)| entity)
+    }
+}
+
+/// How a tower picks between the targets in its range
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TargetPriority {
+    #[default]
+    Closest,
+    /// Furthest along the path
+    First,
+    /// Most health left
+    Strongest,
+    /// Healers, shielders and auras before anything else
+    Support,
+}
+
+impl TargetPriority {
+    pub fn next(self) -> Self {
+        match self {
+            TargetPriority::Closest => TargetPriority::First,
+            TargetPriority::First => TargetPriority::Strongest,
+            TargetPriority::Strongest => TargetPriority::Support,
+            TargetPriority::Support => TargetPriority::Closest,
+        }
+    }
+}
+
+/// Which kinds of targets a tower is able to shoot at
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TargetLayers {
+    #[default]
+    Ground,
+    Air,
+    Both,
+}
+
+impl TargetLayers {
+    pub fn can_hit(&self, flying: bool) -> bool {
+        match self {
+            TargetLayers::Ground => !flying,
+            TargetLayers::Air => flying,
+            TargetLayers::Both => true,
+        }
+    }
+}
+
+#[derive(
+    Inspectable,
+    Reflect,
+    FromReflect,
+    Component,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
 pub enum TowerType {
     Tomato,
     Potato,
     Cabbage,
+    Pepper,
+    Farm,
+    Sprinkler,
+}
+
+/// Empty spots that a tower can be built on
+#[derive(Component)]
+pub struct TowerBase;
+
+#[derive(Resource)]
+pub struct SelectionColliders {
+    pub mesh: Handle<Mesh>,
+    pub default_color: Handle<StandardMaterial>,
+    pub selected_color: Handle<StandardMaterial>,
+}
+
+impl SelectionColliders {
+    /// Adds the see-through capsule that is clicked on to select bases and towers
+    pub fn make_selectable(&self, entity: &mut EntityCommands) {
+        entity
+            .insert(self.mesh.clone())
+            .insert(Highlighting {
+                initial: self.default_color.clone(),
+                hovered: Some(self.selected_color.clone()),
+                pressed: Some(self.selected_color.clone()),
+                selected: Some(self.selected_color.clone()),
+            })
+            .insert(self.default_color.clone())
+            .insert(NotShadowCaster)
+            .insert(PickableBundle::default());
+    }
 }
 
 #[derive(Component)]
@@ -32,14 +213,25 @@ pub struct TowerPlugin;
 impl Plugin for TowerPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Tower>()
+            .register_type::<TargetLayers>()
+            .register_type::<TargetPriority>()
+            .register_type::<TowerType>()
+            .register_type::<TowerBuffs>()
             .register_inspectable::<TowerType>()
             .register_type::<TowerButtonState>()
             .add_system_set(
                 SystemSet::on_update(GameState::Gameplay)
-                    .with_system(tower_shooting)
                     .with_system(tower_button_clicked)
                     .with_system(create_ui_on_selection)
                     .with_system(grey_tower_buttons.after(create_ui_on_selection)),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Attack.set().with_system(tower_shooting),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Actions.set().with_system(build_towers),
             );
     }
 }
@@ -62,50 +254,108 @@ fn grey_tower_buttons(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn tower_shooting(
     mut commands: Commands,
-    mut towers: Query<(Entity, &mut Tower, &TowerType, &GlobalTransform)>,
-    targets: Query<&GlobalTransform, With<Target>>,
+    mut towers: Query<(Entity, &mut Tower, &TowerType, &Transform)>,
+    targets: TargetCandidates,
+    speeds: Query<(&SpeedBuff, Option<&Slowed>)>,
     bullet_assets: Res<GameAssets>,
-    time: Res<Time>,
+    path: Res<TargetPath>,
+    air_path: Res<AirPath>,
+    time: Res<GameTime>,
 ) {
     for (tower_ent, mut tower, tower_type, transform) in &mut towers {
-        tower.shooting_timer.tick(time.delta());
+        //Beams, farms and sprinklers have their own systems
+        let Some((model, mut bullet)) = tower_type.get_bullet(Vec3::ZERO, &bullet_assets) else {
+            continue;
+        };
+        let fire_rate = tower.buffs.fire_rate;
+        tower.shooting_timer.tick(time.delta().mul_f32(fire_rate));
         if tower.shooting_timer.just_finished() {
-            let bullet_spawn = transform.translation() + tower.bullet_offset;
-
-            let direction = targets
-                .iter()
-                .filter(|target_transform| {
-                    Vec3::distance(target_transform.translation(), bullet_spawn) < tower.range
-                })
-                .min_by_key(|target_transform| {
-                    FloatOrd(Vec3::distance(target_transform.translation(), bullet_spawn))
-                })
-                .map(|closest_target| closest_target.translation() - bullet_spawn);
-
-            if let Some(direction) = direction {
-                let (model, bullet) = tower_type.get_bullet(direction, &bullet_assets);
-                commands.entity(tower_ent).with_children(|commands| {
-                    commands
-                        .spawn(SceneBundle {
-                            scene: model,
-                            transform: Transform::from_translation(tower.bullet_offset),
-                            ..Default::default()
-                        })
-                        .insert(Lifetime {
-                            timer: Timer::from_seconds(10.0, TimerMode::Once),
-                        })
-                        .insert(bullet)
-                        .insert(Name::new("Bullet"));
+            let bullet_spawn = transform.translation + tower.bullet_offset;
+
+            let chosen = tower
+                .choose_target(bullet_spawn, &targets)
+                .and_then(|target| targets.get(target).ok());
+
+            if let Some((target_ent, target_transform, target, _, flying, ..)) = chosen {
+                bullet.direction = target_transform.translation - bullet_spawn;
+                bullet.damage = tower.buffed_damage(bullet.damage);
+                let ballistic = tower_type.get_ballistic().map(|mut ballistic| {
+                    // Aim where the target will be when the shot lands
+                    let path = air_path.or_ground(&path, flying.is_some());
+                    let speed = speeds
+                        .get(target_ent)
+                        .map_or(target.speed, |(buff, slowed)| {
+                            target.effective_speed(buff, slowed)
+                        });
+                    let (landing, _) =
+                        path.sample(target.path_distance + speed * ballistic.flight_time);
+                    let landing = if target.path_index == 0 {
+                        target_transform.translation.xz()
+                    } else {
+                        landing
+                    };
+                    // Bullets are children of the tower so the arc is in tower space
+                    ballistic.start = tower.bullet_offset;
+                    ballistic.end = landing.extend(0.0).xzy() - transform.translation;
+                    ballistic
                 });
+
+                spawn_bullet(
+                    &mut commands,
+                    tower_ent,
+                    tower.bullet_offset,
+                    model,
+                    bullet,
+                    ballistic,
+                );
             }
         }
     }
 }
 
+/// Bullets are children of the tower that fired them
+pub fn spawn_bullet(
+    commands: &mut Commands,
+    tower: Entity,
+    offset: Vec3,
+    model: Handle<Scene>,
+    bullet: Bullet,
+    ballistic: Option<Ballistic>,
+) {
+    commands.entity(tower).with_children(|commands| {
+        let mut bullet_entity = commands.spawn(SceneBundle {
+            scene: model,
+            transform: Transform::from_translation(offset),
+            ..Default::default()
+        });
+        bullet_entity
+            .insert(Lifetime {
+                timer: Timer::from_seconds(10.0, TimerMode::Once),
+            })
+            .insert(bullet)
+            .insert(Name::new("Bullet"));
+        if let Some(ballistic) = ballistic {
+            bullet_entity.insert(ballistic);
+        }
+    });
+}
+
 //TODO all of the tower description could be in a hashmap resource loaded on startup from a config file
 impl TowerType {
+    /// In the order of the build buttons
+    pub const ALL: [TowerType; 6] = [
+        TowerType::Tomato,
+        TowerType::Potato,
+        TowerType::Cabbage,
+        TowerType::Pepper,
+        TowerType::Farm,
+        TowerType::Sprinkler,
+    ];
+
+    /// Pepper, Farm and Sprinkler borrow the Tomato, Potato and Cabbage models until they get their own
     fn get_tower(&self, assets: &GameAssets) -> (Handle<Scene>, Tower) {
         match self {
             TowerType::Tomato => (
@@ -114,6 +364,9 @@ impl TowerType {
                     shooting_timer: Timer::from_seconds(0.5, TimerMode::Repeating),
                     bullet_offset: Vec3::new(0.0, 0.6, 0.0),
                     range: 4.5,
+                    layers: TargetLayers::Both,
+                    priority: TargetPriority::Closest,
+                    ..default()
                 },
             ),
             TowerType::Potato => (
@@ -122,6 +375,9 @@ impl TowerType {
                     shooting_timer: Timer::from_seconds(0.7, TimerMode::Repeating),
                     bullet_offset: Vec3::new(0.0, 0.6, 0.0),
                     range: 4.5,
+                    layers: TargetLayers::Ground,
+                    priority: TargetPriority::First,
+                    ..default()
                 },
             ),
             TowerType::Cabbage => (
@@ -130,18 +386,155 @@ impl TowerType {
                     shooting_timer: Timer::from_seconds(0.8, TimerMode::Repeating),
                     bullet_offset: Vec3::new(0.0, 0.6, 0.0),
                     range: 4.5,
+                    layers: TargetLayers::Ground,
+                    priority: TargetPriority::Support,
+                    ..default()
                 },
             ),
+            TowerType::Pepper => (
+                assets.tomato_tower_scene.clone(),
+                Tower {
+                    //Beams don't use the timer, damage is dealt every frame
+                    shooting_timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+                    bullet_offset: Vec3::new(0.0, 0.6, 0.0),
+                    range: 3.5,
+                    layers: TargetLayers::Both,
+                    priority: TargetPriority::First,
+                    ..default()
+                },
+            ),
+            TowerType::Farm => (
+                assets.potato_tower_scene.clone(),
+                Tower {
+                    shooting_timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+                    bullet_offset: Vec3::new(0.0, 0.6, 0.0),
+                    range: 0.0,
+                    ..default()
+                },
+            ),
+            TowerType::Sprinkler => (
+                assets.cabbage_tower_scene.clone(),
+                Tower {
+                    shooting_timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+                    bullet_offset: Vec3::new(0.0, 0.6, 0.0),
+                    range: 0.0,
+                    ..default()
+                },
+            ),
+        }
+    }
+
+    pub fn cost(&self) -> u32 {
+        match self {
+            TowerType::Tomato => 50,
+            TowerType::Potato => 80,
+            TowerType::Cabbage => 110,
+            TowerType::Pepper => 120,
+            TowerType::Farm => 70,
+            TowerType::Sprinkler => 90,
+        }
+    }
+
+    /// Cost to go from this tier to the next, None when there are no more upgrades
+    pub fn upgrade_cost(&self, tier: u32) -> Option<u32> {
+        match (self, tier) {
+            (TowerType::Farm, 1) => Some(60),
+            (TowerType::Farm, 2) => Some(100),
+            (TowerType::Sprinkler, 1) => Some(80),
+            (TowerType::Sprinkler, 2) => Some(120),
+            _ => None,
+        }
+    }
+
+    /// Aura strength for a tier, stronger tiers boost more
+    pub fn get_aura(&self, tier: u32) -> Option<TowerAura> {
+        let bonus = 0.1 + 0.05 * tier as f32;
+        match self {
+            TowerType::Sprinkler => Some(TowerAura {
+                radius: 3.0,
+                fire_rate_bonus: bonus,
+                range_bonus: bonus / 2.0,
+                damage_bonus: 0.0,
+            }),
+            _ => None,
+        }
+    }
+
+    fn get_farm(&self) -> Option<Farm> {
+        match self {
+            TowerType::Farm => Some(Farm {
+                timer: Timer::from_seconds(10.0, TimerMode::Repeating),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Detectors let every tower shoot stealthy targets inside the detection range
+    fn get_detector(&self) -> Option<Detector> {
+        match self {
+            TowerType::Cabbage => Some(Detector { range: 3.5 }),
+            _ => None,
         }
     }
 
-    fn get_bullet(&self, direction: Vec3, assets: &GameAssets) -> (Handle<Scene>, Bullet) {
+    /// Beam towers hold on to one target and damage it every frame instead of firing bullets
+    fn get_beam(&self) -> Option<Beam> {
         match self {
+            TowerType::Pepper => Some(Beam {
+                damage_per_second: 1.5,
+                ramp_per_second: 0.5,
+                max_multiplier: 3.0,
+                damage_type: DamageType::Acid,
+                ..default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// The active ability the player can trigger from the tower panel, if the tower has one
+    pub fn get_ability(&self) -> Option<TowerAbility> {
+        match self {
+            TowerType::Tomato => Some(TowerAbility::new(AbilityKind::Barrage { shots: 8 }, 20.0)),
+            TowerType::Cabbage => Some(TowerAbility::new(
+                AbilityKind::Net {
+                    radius: 1.5,
+                    multiplier: 0.4,
+                    seconds: 4.0,
+                },
+                25.0,
+            )),
+            _ => None,
+        }
+    }
+
+    /// Lobbed shots arc over to the ground instead of flying straight at the target
+    fn get_ballistic(&self) -> Option<Ballistic> {
+        match self {
+            TowerType::Cabbage => Some(Ballistic {
+                apex_height: 2.0,
+                flight_time: 1.2,
+                ..default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// None for towers that don't fire bullets
+    pub fn get_bullet(
+        &self,
+        direction: Vec3,
+        assets: &GameAssets,
+    ) -> Option<(Handle<Scene>, Bullet)> {
+        let bullet = match self {
             TowerType::Tomato => (
                 assets.tomato_scene.clone(),
                 Bullet {
                     direction,
                     speed: 3.5,
+                    damage: 1,
+                    damage_type: DamageType::Acid,
+                    splash_radius: 0.0,
+                    knockback: 0.0,
                 },
             ),
             TowerType::Potato => (
@@ -149,6 +542,10 @@ impl TowerType {
                 Bullet {
                     direction,
                     speed: 6.5,
+                    damage: 1,
+                    damage_type: DamageType::Physical,
+                    splash_radius: 0.0,
+                    knockback: 0.6,
                 },
             ),
             TowerType::Cabbage => (
@@ -156,23 +553,56 @@ impl TowerType {
                 Bullet {
                     direction,
                     speed: 2.5,
+                    damage: 1,
+                    damage_type: DamageType::Nature,
+                    splash_radius: 1.0,
+                    knockback: 0.0,
                 },
             ),
-        }
+            TowerType::Pepper | TowerType::Farm | TowerType::Sprinkler => return None,
+        };
+        Some(bullet)
     }
 }
 
-fn spawn_tower(
+pub fn spawn_tower_base(
+    commands: &mut Commands,
+    assets: &GameAssets,
+    colliders: &SelectionColliders,
+    position: Vec3,
+) -> Entity {
+    let mut base = commands.spawn(SpatialBundle::from_transform(Transform::from_translation(
+        position,
+    )));
+    colliders.make_selectable(&mut base);
+    base.insert(Name::new("Tower_Base"))
+        .insert(TowerBase)
+        .with_children(|commands| {
+            commands.spawn(SceneBundle {
+                scene: assets.tower_base_scene.clone(),
+                transform: Transform::from_xyz(0.0, -0.8, 0.0),
+                ..Default::default()
+            });
+        })
+        .id()
+}
+
+pub fn spawn_tower(
     commands: &mut Commands,
     assets: &GameAssets,
+    colliders: &SelectionColliders,
     position: Vec3,
     tower_type: TowerType,
+    cost: u32,
 ) -> Entity {
-    let (tower_scene, tower) = tower_type.get_tower(assets);
-    commands
-        .spawn(SpatialBundle::from_transform(Transform::from_translation(
-            position,
-        )))
+    let (tower_scene, mut tower) = tower_type.get_tower(assets);
+    tower.tier = 1;
+    tower.invested = cost;
+    let mut entity = commands.spawn(SpatialBundle::from_transform(Transform::from_translation(
+        position,
+    )));
+    colliders.make_selectable(&mut entity);
+    entity
         .insert(Name::new(format!("{:?}_Tower", tower_type)))
         .insert(tower_type)
         .insert(tower)
@@ -182,47 +612,97 @@ fn spawn_tower(
                 transform: Transform::from_xyz(0.0, -0.8, 0.0),
                 ..Default::default()
             });
-        })
-        .id()
+        });
+    if let Some(detector) = tower_type.get_detector() {
+        entity.insert(detector);
+    }
+    if let Some(beam) = tower_type.get_beam() {
+        entity.insert(beam);
+    }
+    if let Some(farm) = tower_type.get_farm() {
+        entity.insert(farm);
+    }
+    if let Some(aura) = tower_type.get_aura(1) {
+        entity.insert(aura);
+    }
+    if let Some(ability) = tower_type.get_ability() {
+        entity.insert(ability);
+    }
+    entity.id()
 }
 
 fn tower_button_clicked(
     interaction: Query<(&Interaction, &TowerType, &TowerButtonState), Changed<Interaction>>,
+    selection: Query<(&Selection, &Transform), With<TowerBase>>,
+    input: ActionInput,
+    game_time: Res<GameTime>,
+    mut actions: EventWriter<PlayerActionRequest>,
+) {
+    let clicked = interaction
+        .iter()
+        .filter(|(interaction, _, _)| matches!(interaction, Interaction::Clicked))
+        .map(|(_, tower_type, _)| *tower_type);
+    let hotkeys = TowerType::ALL
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            !game_time.paused && input.just_pressed(InputAction::BuildTower(*index))
+        })
+        .map(|(_, tower_type)| tower_type);
+    for tower_type in clicked.chain(hotkeys) {
+        for (selection, transform) in &selection {
+            if selection.selected() {
+                actions.send(PlayerActionRequest(PlayerAction::Build {
+                    site: transform.translation,
+                    tower_type,
+                }));
+            }
+        }
+    }
+}
+
+fn build_towers(
     mut commands: Commands,
-    selection: Query<(Entity, &Selection, &Transform)>,
+    mut actions: EventReader<PlayerActionEvent>,
+    bases: Query<(Entity, &Transform), With<TowerBase>>,
     mut player: Query<&mut Player>,
     assets: Res<GameAssets>,
+    colliders: Res<SelectionColliders>,
 ) {
     let mut player = player.single_mut();
-    for (interaction, tower_type, button_state) in &interaction {
-        if matches!(interaction, Interaction::Clicked) {
-            for (entity, selection, transform) in &selection {
-                if selection.selected() {
-                    //can afford (same as checking if affordable is set)
-                    if player.money >= button_state.cost {
-                        player.money -= button_state.cost;
-                        //Remove the base model/hitbox
-                        commands.entity(entity).despawn_recursive();
-
-                        spawn_tower(&mut commands, &assets, transform.translation, *tower_type);
-                    }
-                }
-            }
+    //The base is only despawned at the end of the tick, don't build on it twice
+    let mut built_on = HashSet::new();
+    for PlayerActionEvent(action) in actions.iter() {
+        let PlayerAction::Build { site, tower_type } = action else {
+            continue;
+        };
+        let Some(base) = entity_at(*site, &bases).filter(|base| !built_on.contains(base)) else {
+            continue;
+        };
+        let cost = tower_type.cost();
+        if player.money >= cost {
+            player.money -= cost;
+            built_on.insert(base);
+            //Remove the base model/hitbox
+            commands.entity(base).despawn_recursive();
+            spawn_tower(&mut commands, &assets, &colliders, *site, *tower_type, cost);
         }
     }
 }
 
 fn create_ui(commands: &mut Commands, asset_server: &AssetServer) {
     //TODO move all tower specific data to a resource, probably serialized to a ron file
+    //Towers without an icon get their name written on the button
     let button_icons = [
-        asset_server.load("tomato_tower.png"),
-        asset_server.load("potato_tower.png"),
-        asset_server.load("cabbage_tower.png"),
+        Some(asset_server.load("tomato_tower.png")),
+        Some(asset_server.load("potato_tower.png")),
+        Some(asset_server.load("cabbage_tower.png")),
+        None,
+        None,
+        None,
     ];
 
-    let towers = [TowerType::Tomato, TowerType::Potato, TowerType::Cabbage];
-
-    let costs = [50, 80, 110];
+    let towers = TowerType::ALL;
 
     commands
         .spawn(NodeBundle {
@@ -235,24 +715,41 @@ fn create_ui(commands: &mut Commands, asset_server: &AssetServer) {
         })
         .insert(TowerUIRoot)
         .with_children(|commands| {
-            for i in 0..3 {
-                commands
-                    .spawn(ButtonBundle {
-                        style: Style {
-                            size: Size::new(Val::Percent(15.0 * 9.0 / 16.0), Val::Percent(15.0)),
-                            align_self: AlignSelf::FlexEnd,
-                            margin: UiRect::all(Val::Percent(2.0)),
-                            ..default()
-                        },
-                        image: button_icons[i].clone().into(),
+            for i in 0..towers.len() {
+                let mut button = commands.spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(15.0 * 9.0 / 16.0), Val::Percent(15.0)),
+                        align_self: AlignSelf::FlexEnd,
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::all(Val::Percent(2.0)),
                         ..default()
-                    })
+                    },
+                    image: button_icons[i]
+                        .clone()
+                        .map(UiImage::from)
+                        .unwrap_or_default(),
+                    ..default()
+                });
+                button
                     .insert(TowerButtonState {
-                        cost: costs[i],
+                        cost: towers[i].cost(),
                         //Set in a system right after this one
                         affordable: false,
                     })
                     .insert(towers[i]);
+                if button_icons[i].is_none() {
+                    button.with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            format!("{:?}", towers[i]),
+                            TextStyle {
+                                font: asset_server.load("FiraSans-Bold.ttf"),
+                                font_size: 28.0,
+                                color: Color::BLACK,
+                            },
+                        ));
+                    });
+                }
             }
         });
 }
@@ -261,7 +758,7 @@ fn create_ui_on_selection(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     //Perf could probably be smarter with change detection
-    selections: Query<&Selection>,
+    selections: Query<&Selection, With<TowerBase>>,
     root: Query<Entity, With<TowerUIRoot>>,
 ) {
     let at_least_one_selected = selections.iter().any(|selection| selection.selected());
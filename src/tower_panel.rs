@@ -0,0 +1,359 @@
+use bevy::{ecs::query::QuerySingleError, utils::HashSet};
+
+use crate::*;
+
+#[derive(Component)]
+pub struct TowerPanelRoot;
+
+#[derive(Component)]
+pub struct TowerPanelText;
+
+#[derive(Component)]
+pub struct UpgradeButton;
+
+#[derive(Component)]
+pub struct SellButton;
+
+#[derive(Component)]
+pub struct AbilityButton;
+
+#[derive(Component)]
+pub struct PriorityButton;
+
+pub struct TowerPanelPlugin;
+
+impl Plugin for TowerPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Gameplay)
+                .with_system(create_panel_on_selection)
+                .with_system(update_tower_panel.after(create_panel_on_selection))
+                .with_system(panel_buttons_clicked),
+        )
+        .add_system_set_to_stage(
+            SimulationStage,
+            SimulationStep::Actions
+                .set()
+                .with_system(apply_tower_actions),
+        );
+    }
+}
+
+fn create_panel_on_selection(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    selections: Query<&Selection, With<Tower>>,
+    root: Query<Entity, With<TowerPanelRoot>>,
+) {
+    let tower_selected = selections.iter().any(|selection| selection.selected());
+    match root.get_single() {
+        Ok(root) => {
+            if !tower_selected {
+                commands.entity(root).despawn_recursive();
+            }
+        }
+        //No root exist
+        Err(QuerySingleError::NoEntities(..)) => {
+            if tower_selected {
+                spawn_tower_panel(&mut commands, &asset_server);
+            }
+        }
+        _ => unreachable!("Too many tower panel roots!"),
+    }
+}
+
+fn spawn_tower_panel(commands: &mut Commands, asset_server: &AssetServer) {
+    let text_style = TextStyle {
+        font: asset_server.load("FiraSans-Bold.ttf"),
+        font_size: 24.0,
+        color: Color::BLACK,
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(22.0), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Percent(1.0),
+                    top: Val::Percent(12.0),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            background_color: Color::rgba(1.0, 1.0, 1.0, 0.7).into(),
+            ..default()
+        })
+        .insert(TowerPanelRoot)
+        .with_children(|commands| {
+            commands
+                .spawn(TextBundle::from_section("", text_style.clone()))
+                .insert(TowerPanelText);
+            commands
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Px(40.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::top(Val::Px(8.0)),
+                        ..default()
+                    },
+                    background_color: Color::GREEN.into(),
+                    ..default()
+                })
+                .insert(UpgradeButton)
+                .with_children(|commands| {
+                    commands.spawn(TextBundle::from_section("", text_style.clone()));
+                });
+            commands
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Px(40.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::top(Val::Px(8.0)),
+                        ..default()
+                    },
+                    background_color: Color::ORANGE.into(),
+                    ..default()
+                })
+                .insert(SellButton)
+                .with_children(|commands| {
+                    commands.spawn(TextBundle::from_section("", text_style.clone()));
+                });
+            commands
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Px(40.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::top(Val::Px(8.0)),
+                        ..default()
+                    },
+                    background_color: Color::SILVER.into(),
+                    ..default()
+                })
+                .insert(PriorityButton)
+                .with_children(|commands| {
+                    commands.spawn(TextBundle::from_section("", text_style.clone()));
+                });
+            commands
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Px(40.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::top(Val::Px(8.0)),
+                        ..default()
+                    },
+                    background_color: Color::CYAN.into(),
+                    ..default()
+                })
+                .insert(AbilityButton)
+                .with_children(|commands| {
+                    commands.spawn(TextBundle::from_section("", text_style));
+                });
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn update_tower_panel(
+    towers: Query<(
+        &Selection,
+        &TowerType,
+        &Tower,
+        Option<&Farm>,
+        Option<&TowerAura>,
+        Option<&TowerAbility>,
+    )>,
+    mut panel_text: Query<&mut Text, With<TowerPanelText>>,
+    upgrade_buttons: Query<&Children, With<UpgradeButton>>,
+    sell_buttons: Query<&Children, With<SellButton>>,
+    mut priority_buttons: Query<
+        (&Children, &mut Visibility),
+        (With<PriorityButton>, Without<AbilityButton>),
+    >,
+    mut ability_buttons: Query<(&Children, &mut Visibility), With<AbilityButton>>,
+    mut button_text: Query<&mut Text, Without<TowerPanelText>>,
+) {
+    let Some((_, tower_type, tower, farm, aura, ability)) =
+        towers.iter().find(|(selection, ..)| selection.selected())
+    else {
+        return;
+    };
+
+    for mut text in &mut panel_text {
+        let mut description = format!("{:?} Tower\nTier {}\n", tower_type, tower.tier);
+        if let Some(farm) = farm {
+            description += &format!(
+                "Income: {} per wave\n{} every {:.0}s",
+                farm.income_per_wave(tower.tier),
+                farm.income_per_tick(tower.tier),
+                farm.timer.duration().as_secs_f32()
+            );
+        } else if let Some(aura) = aura {
+            description += &format!(
+                "Aura radius: {:.1}\nFire rate +{:.0}%\nRange +{:.0}%",
+                aura.radius,
+                aura.fire_rate_bonus * 100.0,
+                aura.range_bonus * 100.0
+            );
+        } else {
+            //Base values with the aura buffed ones next to them
+            description += &format!(
+                "Range: {:.1} ({:.1})\nFire rate: x{:.2}\nDamage: x{:.2}\nPriority: {:?}",
+                tower.range,
+                tower.buffed_range(),
+                tower.buffs.fire_rate,
+                tower.buffs.damage,
+                tower.priority
+            );
+        }
+        text.sections[0].value = description;
+    }
+
+    for children in &upgrade_buttons {
+        for child in children {
+            if let Ok(mut text) = button_text.get_mut(*child) {
+                text.sections[0].value = match tower_type.upgrade_cost(tower.tier) {
+                    Some(cost) => format!("Upgrade ({})", cost),
+                    None => "Max tier".to_string(),
+                };
+            }
+        }
+    }
+
+    for children in &sell_buttons {
+        for child in children {
+            if let Ok(mut text) = button_text.get_mut(*child) {
+                text.sections[0].value = format!("Sell ({})", tower.sell_value());
+            }
+        }
+    }
+
+    //Only towers that aim at something have a priority to change
+    for (children, mut visibility) in &mut priority_buttons {
+        visibility.is_visible = tower.range > 0.0;
+        for child in children {
+            if let Ok(mut text) = button_text.get_mut(*child) {
+                text.sections[0].value = format!("Target: {:?}", tower.priority);
+            }
+        }
+    }
+
+    for (children, mut visibility) in &mut ability_buttons {
+        visibility.is_visible = ability.is_some();
+        let Some(ability) = ability else {
+            continue;
+        };
+        for child in children {
+            if let Ok(mut text) = button_text.get_mut(*child) {
+                text.sections[0].value = if ability.ready() {
+                    format!("{} [F]", ability.kind.name())
+                } else {
+                    format!(
+                        "{} ({:.0}s)",
+                        ability.kind.name(),
+                        ability.cooldown.remaining_secs().ceil()
+                    )
+                };
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn panel_buttons_clicked(
+    upgrade: Query<&Interaction, (With<UpgradeButton>, Changed<Interaction>)>,
+    sell: Query<&Interaction, (With<SellButton>, Changed<Interaction>)>,
+    priority: Query<&Interaction, (With<PriorityButton>, Changed<Interaction>)>,
+    ability: Query<&Interaction, (With<AbilityButton>, Changed<Interaction>)>,
+    towers: Query<(&Selection, &Transform), With<Tower>>,
+    input: ActionInput,
+    game_time: Res<GameTime>,
+    mut actions: EventWriter<PlayerActionRequest>,
+) {
+    let Some(tower) = towers
+        .iter()
+        .find(|(selection, _)| selection.selected())
+        .map(|(_, transform)| transform.translation)
+    else {
+        return;
+    };
+    //Hotkeys would pile up behind the menu and all go through at once when it closes
+    let hotkeys = !game_time.paused;
+    if clicked(upgrade.iter()) || hotkeys && input.just_pressed(InputAction::Upgrade) {
+        actions.send(PlayerActionRequest(PlayerAction::Upgrade { tower }));
+    }
+    if clicked(sell.iter()) || hotkeys && input.just_pressed(InputAction::Sell) {
+        actions.send(PlayerActionRequest(PlayerAction::Sell { tower }));
+    }
+    if clicked(priority.iter()) {
+        actions.send(PlayerActionRequest(PlayerAction::CyclePriority { tower }));
+    }
+    if clicked(ability.iter()) {
+        actions.send(PlayerActionRequest(PlayerAction::UseAbility { tower }));
+    }
+}
+
+fn clicked<'a>(mut interactions: impl Iterator<Item = &'a Interaction>) -> bool {
+    interactions.any(|interaction| matches!(interaction, Interaction::Clicked))
+}
+
+fn apply_tower_actions(
+    mut commands: Commands,
+    mut actions: EventReader<PlayerActionEvent>,
+    mut towers: Query<(Entity, &TowerType, &mut Tower, &Transform)>,
+    mut player: Query<&mut Player>,
+    assets: Res<GameAssets>,
+    colliders: Res<SelectionColliders>,
+) {
+    let mut player = player.single_mut();
+    //Despawns wait for the end of the tick, a second action on a sold tower would still find it
+    let mut sold = HashSet::new();
+    for PlayerActionEvent(action) in actions.iter() {
+        let position = match action {
+            PlayerAction::Upgrade { tower }
+            | PlayerAction::Sell { tower }
+            | PlayerAction::CyclePriority { tower } => *tower,
+            _ => continue,
+        };
+        let found = entity_at(
+            position,
+            towers
+                .iter()
+                .map(|(entity, _, _, transform)| (entity, transform)),
+        );
+        let Some(entity) = found.filter(|entity| !sold.contains(entity)) else {
+            continue;
+        };
+        let Ok((entity, tower_type, mut tower, _)) = towers.get_mut(entity) else {
+            continue;
+        };
+
+        match action {
+            PlayerAction::Upgrade { .. } => {
+                if let Some(cost) = tower_type.upgrade_cost(tower.tier) {
+                    if player.money >= cost {
+                        player.money -= cost;
+                        tower.tier += 1;
+                        tower.invested += cost;
+                        if let Some(aura) = tower_type.get_aura(tower.tier) {
+                            commands.entity(entity).insert(aura);
+                        }
+                    }
+                }
+            }
+            PlayerAction::Sell { .. } => {
+                player.money += tower.sell_value();
+                sold.insert(entity);
+                commands.entity(entity).despawn_recursive();
+                spawn_tower_base(&mut commands, &assets, &colliders, position);
+            }
+            PlayerAction::CyclePriority { .. } => tower.priority = tower.priority.next(),
+            _ => unreachable!(),
+        }
+    }
+}
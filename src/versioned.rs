@@ -0,0 +1,93 @@
+use serde::Deserialize;
+
+/// One step of a migration chain, rewrites a file from version `from` to `from + 1`.
+/// Steps work on the text so they can still read fields the current types no longer have.
+pub struct Migration {
+    pub from: u32,
+    pub migrate: fn(String) -> Result<String, String>,
+}
+
+#[derive(Deserialize)]
+struct VersionHeader {
+    //Files from before the version was written down
+    #[serde(default = "first_version")]
+    version: u32,
+}
+
+fn first_version() -> u32 {
+    1
+}
+
+/// Version of a RON file with a top level `version` field, files from before it existed count as 1
+pub fn read_version(text: &str) -> Result<u32, String> {
+    let header: VersionHeader = ron::from_str(text).map_err(|err| err.to_string())?;
+    Ok(header.version)
+}
+
+/// Runs every migration step between the file's version and the current one
+pub fn upgrade(text: String, current: u32, migrations: &[Migration]) -> Result<String, String> {
+    let mut version = read_version(&text)?;
+    if version > current {
+        return Err(format!(
+            "file is version {}, made by a newer version of the game (this one reads up to {})",
+            version, current
+        ));
+    }
+
+    let mut text = text;
+    while version < current {
+        let step = migrations
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or_else(|| format!("no way to upgrade files from version {}", version))?;
+        text = (step.migrate)(text)
+            .map_err(|err| format!("upgrading from version {} failed: {}", version, err))?;
+        version += 1;
+    }
+    Ok(text)
+}
+
+/// Example files of every version, kept so old files are known to still load
+#[cfg(test)]
+pub fn fixture(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bump(text: String) -> Result<String, String> {
+        Ok(text.replacen("version: 1", "version: 2", 1))
+    }
+
+    const MIGRATIONS: &[Migration] = &[Migration {
+        from: 1,
+        migrate: bump,
+    }];
+
+    #[test]
+    fn missing_version_counts_as_first() {
+        assert_eq!(read_version("(volume: 0.5)"), Ok(1));
+    }
+
+    #[test]
+    fn runs_migrations_up_to_current() {
+        let text = upgrade("(version: 1)".to_string(), 2, MIGRATIONS).unwrap();
+        assert_eq!(read_version(&text), Ok(2));
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let err = upgrade("(version: 3)".to_string(), 2, MIGRATIONS).unwrap_err();
+        assert!(err.contains("newer version"));
+    }
+
+    #[test]
+    fn reports_missing_migration() {
+        let err = upgrade("(version: 1)".to_string(), 3, MIGRATIONS).unwrap_err();
+        assert!(err.contains("from version 2"));
+    }
+}
@@ -0,0 +1,285 @@
+use std::{collections::VecDeque, time::Duration};
+
+use bevy::{math::Vec3Swizzles, prelude::*};
+
+use crate::*;
+
+pub struct Wave {
+    pub enemies: Vec<(EnemyType, u32)>,
+    pub spawn_interval: f32,
+}
+
+//Could be loaded from a config or level file
+#[derive(Resource)]
+pub struct Waves {
+    pub waves: Vec<Wave>,
+    pub break_seconds: f32,
+}
+
+#[derive(Resource)]
+pub struct WaveState {
+    /// Number of waves started so far, the current wave while one is running
+    pub wave: usize,
+    pub in_progress: bool,
+    pub remaining: VecDeque<EnemyType>,
+    pub spawn_timer: Timer,
+    pub break_timer: Timer,
+}
+
+pub struct WaveStartEvent {
+    pub wave: usize,
+}
+
+pub struct WaveClearedEvent {
+    pub wave: usize,
+}
+
+#[derive(Component)]
+pub struct WaveUI;
+
+#[derive(Component)]
+pub struct CallWaveButton;
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        let waves = Waves {
+            waves: vec![
+                Wave {
+                    enemies: vec![(EnemyType::Basic, 10)],
+                    spawn_interval: 1.5,
+                },
+                Wave {
+                    enemies: vec![
+                        (EnemyType::Basic, 12),
+                        (EnemyType::Flyer, 4),
+                        (EnemyType::Splitter, 3),
+                        (EnemyType::Healer, 2),
+                        (EnemyType::Ghost, 3),
+                    ],
+                    spawn_interval: 1.2,
+                },
+                Wave {
+                    enemies: vec![
+                        (EnemyType::Basic, 15),
+                        (EnemyType::Flyer, 6),
+                        (EnemyType::Splitter, 5),
+                        (EnemyType::Shielder, 2),
+                        (EnemyType::Hastener, 2),
+                        (EnemyType::Ghost, 5),
+                        (EnemyType::Knight, 4),
+                        (EnemyType::Boss, 1),
+                    ],
+                    spawn_interval: 1.0,
+                },
+            ],
+            break_seconds: 5.0,
+        };
+        app.insert_resource(WaveState::new(waves.break_seconds))
+            .insert_resource(waves)
+            .add_simulation_event::<WaveStartEvent>()
+            .add_simulation_event::<WaveClearedEvent>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Gameplay)
+                    .with_system(reset_waves)
+                    .with_system(spawn_call_wave_button),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Gameplay)
+                    .with_system(update_wave_ui)
+                    .with_system(call_wave_input),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Actions.set().with_system(call_wave),
+            )
+            .add_system_set_to_stage(
+                SimulationStage,
+                SimulationStep::Spawn
+                    .set()
+                    .with_system(start_waves)
+                    // Before spawning so a target spawned this tick is counted next tick
+                    .with_system(wave_cleared.after(start_waves))
+                    .with_system(spawn_wave_targets.after(wave_cleared)),
+            );
+    }
+}
+
+impl WaveState {
+    fn new(break_seconds: f32) -> Self {
+        WaveState {
+            wave: 0,
+            in_progress: false,
+            remaining: VecDeque::new(),
+            spawn_timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+            break_timer: Timer::from_seconds(break_seconds, TimerMode::Once),
+        }
+    }
+}
+
+fn reset_waves(mut state: ResMut<WaveState>, waves: Res<Waves>) {
+    *state = WaveState::new(waves.break_seconds);
+}
+
+fn start_waves(
+    mut state: ResMut<WaveState>,
+    waves: Res<Waves>,
+    time: Res<GameTime>,
+    mut start_events: EventWriter<WaveStartEvent>,
+) {
+    if state.in_progress || state.wave >= waves.waves.len() {
+        return;
+    }
+
+    state.break_timer.tick(time.delta());
+    if state.break_timer.just_finished() {
+        let wave = &waves.waves[state.wave];
+        state.remaining = wave
+            .enemies
+            .iter()
+            .flat_map(|(enemy_type, count)| (0..*count).map(|_| *enemy_type))
+            .collect();
+        state.spawn_timer = Timer::from_seconds(wave.spawn_interval, TimerMode::Repeating);
+        state.in_progress = true;
+        state.wave += 1;
+        start_events.send(WaveStartEvent { wave: state.wave });
+    }
+}
+
+fn spawn_wave_targets(
+    mut commands: Commands,
+    mut state: ResMut<WaveState>,
+    assets: Res<GameAssets>,
+    level: Res<Level>,
+    path: Res<TargetPath>,
+    air_path: Res<AirPath>,
+    time: Res<GameTime>,
+) {
+    if !state.in_progress {
+        return;
+    }
+
+    state.spawn_timer.tick(time.delta());
+    if state.spawn_timer.just_finished() {
+        if let Some(enemy_type) = state.remaining.pop_front() {
+            let start = air_path.or_ground(&path, enemy_type.is_flying()).start();
+            spawn_target(
+                &mut commands,
+                &assets,
+                enemy_type,
+                start.extend(enemy_type.height(&level)).xzy(),
+                0,
+                0.0,
+            );
+        }
+    }
+}
+
+fn wave_cleared(
+    mut state: ResMut<WaveState>,
+    waves: Res<Waves>,
+    targets: Query<(), With<Target>>,
+    mut cleared_events: EventWriter<WaveClearedEvent>,
+    mut stats: ResMut<GameStats>,
+    mut game_over: EventWriter<GameOverEvent>,
+) {
+    if state.in_progress && state.remaining.is_empty() && targets.is_empty() {
+        state.in_progress = false;
+        state.break_timer.reset();
+        cleared_events.send(WaveClearedEvent { wave: state.wave });
+        stats.waves_cleared += 1;
+
+        if state.wave >= waves.waves.len() {
+            info!("ALL WAVES CLEARED");
+            game_over.send(GameOverEvent { won: true });
+        }
+    }
+}
+
+fn call_wave_input(
+    input: ActionInput,
+    game_time: Res<GameTime>,
+    interactions: Query<&Interaction, (With<CallWaveButton>, Changed<Interaction>)>,
+    mut actions: EventWriter<PlayerActionRequest>,
+) {
+    let clicked = interactions
+        .iter()
+        .any(|interaction| matches!(interaction, Interaction::Clicked));
+    if clicked || !game_time.paused && input.just_pressed(InputAction::CallWave) {
+        actions.send(PlayerActionRequest(PlayerAction::CallWave));
+    }
+}
+
+/// Ends the break early, the next wave starts on the following tick
+fn call_wave(
+    mut actions: EventReader<PlayerActionEvent>,
+    mut state: ResMut<WaveState>,
+    waves: Res<Waves>,
+) {
+    for PlayerActionEvent(action) in actions.iter() {
+        if matches!(action, PlayerAction::CallWave)
+            && !state.in_progress
+            && state.wave < waves.waves.len()
+        {
+            let duration = state.break_timer.duration();
+            //Leaves the last tick of the break so start_waves sees it finish
+            state
+                .break_timer
+                .set_elapsed(duration.saturating_sub(Duration::from_nanos(1)));
+        }
+    }
+}
+
+fn spawn_call_wave_button(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(128.0), Val::Px(30.0)),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Percent(1.0),
+                    top: Val::Percent(14.0),
+                    ..default()
+                },
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(CallWaveButton)
+        .insert(Name::new("CallWaveButton"))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section(
+                "Call wave [N]",
+                TextStyle {
+                    font: asset_server.load("FiraSans-Bold.ttf"),
+                    font_size: 20.0,
+                    color: Color::BLACK,
+                },
+            ));
+        });
+}
+
+fn update_wave_ui(
+    state: Res<WaveState>,
+    waves: Res<Waves>,
+    mut wave_ui: Query<&mut Text, With<WaveUI>>,
+    mut call_buttons: Query<&mut Visibility, With<CallWaveButton>>,
+) {
+    for mut visibility in &mut call_buttons {
+        visibility.is_visible = !state.in_progress && state.wave < waves.waves.len();
+    }
+    for mut text in &mut wave_ui {
+        let label = if state.in_progress || state.wave >= waves.waves.len() {
+            format!("Wave: {}/{}", state.wave, waves.waves.len())
+        } else {
+            format!(
+                "Next wave in {:.0}",
+                state.break_timer.remaining_secs().ceil()
+            )
+        };
+        *text = Text::from_section(label, text.sections[0].style.clone());
+    }
+}